@@ -7,7 +7,7 @@ use std::fs::File;
 use std::io::{BufReader, BufWriter};
 use std::iter::FromIterator;
 
-use instant_distance::Point;
+use instant_distance::{simd, Point};
 use pyo3::exceptions::{PyTypeError, PyValueError};
 use pyo3::types::{PyAnyMethods, PyList, PyListMethods, PyModule, PyModuleMethods, PyString};
 use pyo3::{pyclass, pymethods, pymodule, Bound, IntoPyObject};
@@ -284,6 +284,12 @@ struct Heuristic {
     /// Whether to keep pruned neighbors to make the neighbor set size constant
     #[pyo3(get, set)]
     keep_pruned: bool,
+    /// Relaxation factor for pruning neighbors
+    ///
+    /// Values above 1.0 (around 1.2 is common) keep more long-range links, which can improve
+    /// recall on high-dimensional data.
+    #[pyo3(get, set)]
+    alpha: f32,
 }
 
 #[pymethods]
@@ -294,10 +300,12 @@ impl Heuristic {
         let instant_distance::Heuristic {
             extend_candidates,
             keep_pruned,
+            alpha,
         } = default;
         Self {
             extend_candidates,
             keep_pruned,
+            alpha,
         }
     }
 }
@@ -307,6 +315,7 @@ impl Default for Heuristic {
         Self {
             extend_candidates: false,
             keep_pruned: true,
+            alpha: 1.0,
         }
     }
 }
@@ -316,10 +325,12 @@ impl From<Heuristic> for instant_distance::Heuristic {
         let Heuristic {
             extend_candidates,
             keep_pruned,
+            alpha,
         } = py;
         Self {
             extend_candidates,
             keep_pruned,
+            alpha,
         }
     }
 }
@@ -356,7 +367,6 @@ impl Neighbor {
     }
 }
 
-#[repr(align(32))]
 #[derive(Clone, Deserialize, Serialize)]
 struct FloatArray(#[serde(with = "BigArray")] [f32; DIMENSIONS]);
 
@@ -377,46 +387,7 @@ impl TryFrom<&Bound<'_, PyAny>> for FloatArray {
 
 impl Point for FloatArray {
     fn distance(&self, rhs: &Self) -> f32 {
-        #[cfg(target_arch = "x86_64")]
-        {
-            use std::arch::x86_64::{
-                _mm256_castps256_ps128, _mm256_extractf128_ps, _mm256_fmadd_ps, _mm256_load_ps,
-                _mm256_setzero_ps, _mm256_sub_ps, _mm_add_ps, _mm_add_ss, _mm_cvtss_f32,
-                _mm_fmadd_ps, _mm_load_ps, _mm_movehl_ps, _mm_shuffle_ps, _mm_sub_ps,
-            };
-            debug_assert_eq!(self.0.len() % 8, 4);
-
-            unsafe {
-                let mut acc_8x = _mm256_setzero_ps();
-                for (lh_slice, rh_slice) in self.0.chunks_exact(8).zip(rhs.0.chunks_exact(8)) {
-                    let lh_8x = _mm256_load_ps(lh_slice.as_ptr());
-                    let rh_8x = _mm256_load_ps(rh_slice.as_ptr());
-                    let diff = _mm256_sub_ps(lh_8x, rh_8x);
-                    acc_8x = _mm256_fmadd_ps(diff, diff, acc_8x);
-                }
-
-                let mut acc_4x = _mm256_extractf128_ps(acc_8x, 1); // upper half
-                let right = _mm256_castps256_ps128(acc_8x); // lower half
-                acc_4x = _mm_add_ps(acc_4x, right); // sum halves
-
-                let lh_4x = _mm_load_ps(self.0[DIMENSIONS - 4..].as_ptr());
-                let rh_4x = _mm_load_ps(rhs.0[DIMENSIONS - 4..].as_ptr());
-                let diff = _mm_sub_ps(lh_4x, rh_4x);
-                acc_4x = _mm_fmadd_ps(diff, diff, acc_4x);
-
-                let lower = _mm_movehl_ps(acc_4x, acc_4x);
-                acc_4x = _mm_add_ps(acc_4x, lower);
-                let upper = _mm_shuffle_ps(acc_4x, acc_4x, 0x1);
-                acc_4x = _mm_add_ss(acc_4x, upper);
-                _mm_cvtss_f32(acc_4x)
-            }
-        }
-        #[cfg(not(target_arch = "x86_64"))]
-        self.0
-            .iter()
-            .zip(rhs.0.iter())
-            .map(|(&a, &b)| (a - b).powi(2))
-            .sum::<f32>()
+        simd::l2_squared(&self.0, &rhs.0)
     }
 }
 
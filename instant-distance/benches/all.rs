@@ -2,6 +2,7 @@ use bencher::{benchmark_group, benchmark_main, Bencher};
 use rand::rngs::StdRng;
 use rand::{Rng, SeedableRng};
 
+use instant_distance::vector::FloatArray;
 use instant_distance::Builder;
 
 benchmark_main!(benches);
@@ -10,7 +11,7 @@ benchmark_group!(benches, build_heuristic);
 fn build_heuristic(bench: &mut Bencher) {
     let mut rng = StdRng::seed_from_u64(SEED);
     let points = (0..1024)
-        .map(|_| Point(rng.random(), rng.random()))
+        .map(|_| FloatArray::<2>::new([rng.random(), rng.random()]))
         .collect::<Vec<_>>();
 
     bench.iter(|| Builder::default().seed(SEED).build_hnsw(points.clone()))
@@ -45,13 +46,3 @@ fn randomized(builder: Builder) -> (u64, usize) {
     (seed, forced.intersection(&found).count())
 }
 */
-
-#[derive(Clone, Copy, Debug)]
-struct Point(f32, f32);
-
-impl instant_distance::Point for Point {
-    fn distance(&self, other: &Self) -> f32 {
-        // Euclidean distance metric
-        ((self.0 - other.0).powi(2) + (self.1 - other.1).powi(2)).sqrt()
-    }
-}
@@ -0,0 +1,144 @@
+//! Bit vector types implementing `Point`, compared by Hamming distance
+//!
+//! Bits are packed into `u64` words, with bit `i` stored in word `i / 64` at position `i % 64`.
+//! Float embeddings can be converted to bit vectors by their signs with `from_signs()`.
+
+use std::ops::Deref;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde-big-array")]
+use serde_big_array::BigArray;
+
+use crate::{simd, Point};
+
+/// A fixed-size bit vector of `WORDS * 64` bits
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BitArray<const WORDS: usize> {
+    #[cfg_attr(feature = "serde", serde(with = "BigArray"))]
+    words: [u64; WORDS],
+}
+
+impl<const WORDS: usize> BitArray<WORDS> {
+    /// Create a new bit vector from the given `words`
+    pub fn new(words: [u64; WORDS]) -> Self {
+        Self { words }
+    }
+
+    /// Create a bit vector with a bit set for each positive value in `vector`
+    ///
+    /// Panics if `vector` has more than `WORDS * 64` values; any remaining bits are unset.
+    pub fn from_signs(vector: &[f32]) -> Self {
+        assert!(vector.len() <= WORDS * 64, "vector too long");
+        let mut words = [0; WORDS];
+        binarize(vector, &mut words);
+        Self { words }
+    }
+
+    /// Return the vector's words
+    pub fn into_inner(self) -> [u64; WORDS] {
+        self.words
+    }
+}
+
+impl<const WORDS: usize> Point for BitArray<WORDS> {
+    fn distance(&self, other: &Self) -> f32 {
+        simd::hamming(&self.words, &other.words) as f32
+    }
+}
+
+impl<const WORDS: usize> From<[u64; WORDS]> for BitArray<WORDS> {
+    fn from(words: [u64; WORDS]) -> Self {
+        Self::new(words)
+    }
+}
+
+impl<const WORDS: usize> Deref for BitArray<WORDS> {
+    type Target = [u64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.words
+    }
+}
+
+impl<const WORDS: usize> AsRef<[u64]> for BitArray<WORDS> {
+    fn as_ref(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+/// A bit vector with a length chosen at runtime
+///
+/// All vectors in an index must have the same number of words.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct BitVec {
+    words: Box<[u64]>,
+}
+
+impl BitVec {
+    /// Create a new bit vector from the given `words`
+    pub fn new(words: impl Into<Box<[u64]>>) -> Self {
+        Self {
+            words: words.into(),
+        }
+    }
+
+    /// Create a bit vector with a bit set for each positive value in `vector`
+    ///
+    /// The length of the bit vector is rounded up to a multiple of 64 bits.
+    pub fn from_signs(vector: &[f32]) -> Self {
+        let mut words = vec![0; vector.len().div_ceil(64)];
+        binarize(vector, &mut words);
+        Self::new(words)
+    }
+
+    /// Return the vector's words
+    pub fn into_inner(self) -> Box<[u64]> {
+        self.words
+    }
+}
+
+impl Point for BitVec {
+    fn distance(&self, other: &Self) -> f32 {
+        simd::hamming(&self.words, &other.words) as f32
+    }
+}
+
+impl From<Vec<u64>> for BitVec {
+    fn from(words: Vec<u64>) -> Self {
+        Self::new(words)
+    }
+}
+
+impl From<Box<[u64]>> for BitVec {
+    fn from(words: Box<[u64]>) -> Self {
+        Self::new(words)
+    }
+}
+
+impl Deref for BitVec {
+    type Target = [u64];
+
+    fn deref(&self) -> &Self::Target {
+        &self.words
+    }
+}
+
+impl AsRef<[u64]> for BitVec {
+    fn as_ref(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+/// Set the bits in `words` for the positive values in `vector`
+fn binarize(vector: &[f32], words: &mut [u64]) {
+    for (chunk, word) in vector.chunks(64).zip(words) {
+        *word = chunk
+            .iter()
+            .enumerate()
+            .filter(|(_, &x)| x > 0.0)
+            .fold(0, |word, (i, _)| word | 1 << i);
+    }
+}
@@ -0,0 +1,221 @@
+//! Geographic points with great-circle distances in meters
+//!
+//! A `LatLng` is a position in degrees of latitude and longitude. As a `Point`, positions are
+//! compared with the haversine distance on a sphere with the Earth's mean radius, which is
+//! accurate to about 0.5%. The `Vincenty` metric computes distances on the WGS 84 ellipsoid
+//! instead, accurate to within a millimeter at a higher cost; pass it to
+//! `Builder::build_with_metric()` to use it.
+//!
+//! Use `Hnsw::search_radius()` to find all points within a distance in meters:
+//!
+//! ```
+//! use instant_distance::geo::LatLng;
+//! use instant_distance::{Builder, Search};
+//!
+//! let stores = vec![
+//!     LatLng::new(52.3731, 4.8926),  // Amsterdam
+//!     LatLng::new(52.0907, 5.1214),  // Utrecht
+//!     LatLng::new(51.9244, 4.4777),  // Rotterdam
+//!     LatLng::new(48.8566, 2.3522),  // Paris
+//! ];
+//! let map = Builder::default().build(stores, vec!["Amsterdam", "Utrecht", "Rotterdam", "Paris"]);
+//!
+//! let mut search = Search::default();
+//! let near = map.search_radius(&LatLng::new(52.15, 5.0), 50_000.0, &mut search);
+//! let names = near.map(|item| *item.value).collect::<Vec<_>>();
+//! assert_eq!(names, ["Utrecht", "Amsterdam", "Rotterdam"]);
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::metric::{serde_by_name, Metric};
+use crate::store::PointStore;
+use crate::{Hnsw, HnswMap, Item, MapItem, Point, Search};
+
+/// A position on Earth, in degrees of latitude and longitude
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LatLng {
+    lat: f64,
+    lng: f64,
+}
+
+impl LatLng {
+    /// Create a position from its latitude and longitude in degrees
+    ///
+    /// Panics if the latitude is not within -90 to 90 degrees, or the longitude is not within -180
+    /// to 180 degrees.
+    pub fn new(lat: f64, lng: f64) -> Self {
+        assert!((-90.0..=90.0).contains(&lat), "latitude out of range");
+        assert!((-180.0..=180.0).contains(&lng), "longitude out of range");
+        Self { lat, lng }
+    }
+
+    /// The latitude in degrees
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+
+    /// The longitude in degrees
+    pub fn lng(&self) -> f64 {
+        self.lng
+    }
+}
+
+impl Point for LatLng {
+    fn distance(&self, other: &Self) -> f32 {
+        Haversine.distance(self, other)
+    }
+}
+
+/// Great-circle distance in meters on a sphere with the Earth's mean radius
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Haversine;
+
+impl Metric<LatLng> for Haversine {
+    fn distance(&self, a: &LatLng, b: &LatLng) -> f32 {
+        let (lat1, lat2) = (a.lat.to_radians(), b.lat.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lng = (b.lng - a.lng).to_radians();
+
+        let h = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lng / 2.0).sin().powi(2);
+        (2.0 * MEAN_RADIUS * h.sqrt().min(1.0).asin()) as f32
+    }
+}
+
+/// Geodesic distance in meters on the WGS 84 ellipsoid, using Vincenty's formulae
+///
+/// The iteration does not converge for nearly antipodal positions; the haversine distance is
+/// used for those.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Vincenty;
+
+impl Metric<LatLng> for Vincenty {
+    fn distance(&self, a: &LatLng, b: &LatLng) -> f32 {
+        match vincenty(a, b) {
+            Some(distance) => distance as f32,
+            None => Haversine.distance(a, b),
+        }
+    }
+}
+
+serde_by_name!(Haversine, Vincenty);
+
+/// Vincenty's inverse formula, or `None` if it does not converge
+fn vincenty(a: &LatLng, b: &LatLng) -> Option<f64> {
+    let minor = (1.0 - FLATTENING) * MAJOR_AXIS;
+    let l = (b.lng - a.lng).to_radians();
+    let u1 = ((1.0 - FLATTENING) * a.lat.to_radians().tan()).atan();
+    let u2 = ((1.0 - FLATTENING) * b.lat.to_radians().tan()).atan();
+    let (sin_u1, cos_u1) = u1.sin_cos();
+    let (sin_u2, cos_u2) = u2.sin_cos();
+
+    let mut lambda = l;
+    for _ in 0..VINCENTY_ITERATIONS {
+        let (sin_lambda, cos_lambda) = lambda.sin_cos();
+        let sin_sigma = ((cos_u2 * sin_lambda).powi(2)
+            + (cos_u1 * sin_u2 - sin_u1 * cos_u2 * cos_lambda).powi(2))
+        .sqrt();
+        if sin_sigma == 0.0 {
+            return Some(0.0);
+        }
+
+        let cos_sigma = sin_u1 * sin_u2 + cos_u1 * cos_u2 * cos_lambda;
+        let sigma = sin_sigma.atan2(cos_sigma);
+        let sin_alpha = cos_u1 * cos_u2 * sin_lambda / sin_sigma;
+        let cos2_alpha = 1.0 - sin_alpha * sin_alpha;
+        // On the equator, `cos2_alpha` is zero and the midpoint term is unused
+        let cos_2sigma_m = match cos2_alpha != 0.0 {
+            true => cos_sigma - 2.0 * sin_u1 * sin_u2 / cos2_alpha,
+            false => 0.0,
+        };
+
+        let c = FLATTENING / 16.0 * cos2_alpha * (4.0 + FLATTENING * (4.0 - 3.0 * cos2_alpha));
+        let prev = lambda;
+        lambda = l
+            + (1.0 - c)
+                * FLATTENING
+                * sin_alpha
+                * (sigma
+                    + c * sin_sigma
+                        * (cos_2sigma_m + c * cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))));
+        if (lambda - prev).abs() > 1e-12 {
+            continue;
+        }
+
+        let u_sq = cos2_alpha * (MAJOR_AXIS.powi(2) - minor.powi(2)) / minor.powi(2);
+        let big_a =
+            1.0 + u_sq / 16384.0 * (4096.0 + u_sq * (-768.0 + u_sq * (320.0 - 175.0 * u_sq)));
+        let big_b = u_sq / 1024.0 * (256.0 + u_sq * (-128.0 + u_sq * (74.0 - 47.0 * u_sq)));
+        let delta_sigma = big_b
+            * sin_sigma
+            * (cos_2sigma_m
+                + big_b / 4.0
+                    * (cos_sigma * (-1.0 + 2.0 * cos_2sigma_m.powi(2))
+                        - big_b / 6.0
+                            * cos_2sigma_m
+                            * (-3.0 + 4.0 * sin_sigma.powi(2))
+                            * (-3.0 + 4.0 * cos_2sigma_m.powi(2))));
+        return Some(minor * big_a * (sigma - delta_sigma));
+    }
+
+    None
+}
+
+impl<D: Metric<LatLng>, S: PointStore<Point = LatLng>> Hnsw<LatLng, D, S> {
+    /// Search the index for the points within `meters` of `center`, nearest first
+    ///
+    /// The search is repeated with a larger `ef` until it reaches a point beyond the radius, so
+    /// the number of results is not limited by `ef_search`. Like other searches, the results are
+    /// approximate and may miss some points within the radius.
+    pub fn search_radius<'a, 'b: 'a>(
+        &'b self,
+        center: &LatLng,
+        meters: f32,
+        search: &'a mut Search,
+    ) -> impl Iterator<Item = Item<'b, LatLng>> + 'a {
+        let mut ef = self.ef_search.max(1);
+        loop {
+            self.search_with(center, &[], ef, search);
+            let exhausted = search.nearest.len() < ef || ef >= self.points.len();
+            match search.nearest.last() {
+                Some(last) if !exhausted && last.distance.into_inner() <= meters => ef *= 2,
+                _ => break,
+            }
+        }
+
+        search
+            .iter()
+            .take_while(move |candidate| candidate.distance.into_inner() <= meters)
+            .map(move |candidate| Item::new(candidate, &self.points))
+    }
+}
+
+impl<V: Clone, D: Metric<LatLng>, S: PointStore<Point = LatLng>> HnswMap<LatLng, V, D, S> {
+    /// Search the index for the points within `meters` of `center`, nearest first
+    ///
+    /// See `Hnsw::search_radius()` for details.
+    pub fn search_radius<'a>(
+        &'a self,
+        center: &LatLng,
+        meters: f32,
+        search: &'a mut Search,
+    ) -> impl Iterator<Item = MapItem<'a, LatLng, V>> + 'a {
+        self.hnsw
+            .search_radius(center, meters, search)
+            .map(move |item| MapItem::from(item, self))
+    }
+}
+
+/// The Earth's mean radius in meters
+const MEAN_RADIUS: f64 = 6_371_008.8;
+
+/// The semi-major axis of the WGS 84 ellipsoid in meters
+const MAJOR_AXIS: f64 = 6_378_137.0;
+
+/// The flattening of the WGS 84 ellipsoid
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+/// Maximum number of iterations in `vincenty()`
+const VINCENTY_ITERATIONS: usize = 200;
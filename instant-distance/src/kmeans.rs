@@ -0,0 +1,302 @@
+//! k-means clustering of dense `f32` vectors
+//!
+//! `KMeans` clusters vectors by Euclidean distance, starting from centroids chosen with k-means++
+//! and refining them with Lloyd's algorithm, or with mini-batch updates for large sets of vectors.
+//! Each iteration assigns every vector to its nearest centroid. For large numbers of clusters, the
+//! nearest centroids are found by searching a temporary `Hnsw` built over the centroids, which is
+//! much faster than comparing each vector to every centroid but may assign some vectors to a
+//! centroid that is not quite the nearest.
+//!
+//! ```
+//! use instant_distance::kmeans::KMeans;
+//!
+//! let vectors = vec![[0.0, 0.1], [0.1, 0.0], [5.0, 5.1], [5.1, 5.0]];
+//! let clusters = KMeans::new(2).seed(7).fit(&vectors);
+//! assert_eq!(clusters.assignments()[0], clusters.assignments()[1]);
+//! assert_ne!(clusters.assignments()[0], clusters.assignments()[2]);
+//! assert_eq!(clusters.assign(&[4.0, 4.0]), clusters.assignments()[3]);
+//! ```
+
+use rand::rngs::SmallRng;
+use rand::seq::index;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::metric::SquaredEuclidean;
+use crate::store::{Flat, Mapped};
+use crate::{simd, Builder, Search};
+
+/// Parameters for k-means clustering
+#[derive(Clone, Debug)]
+pub struct KMeans {
+    k: usize,
+    iterations: usize,
+    batch: Option<usize>,
+    seed: u64,
+}
+
+impl KMeans {
+    /// Cluster vectors into `k` clusters
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            iterations: 25,
+            batch: None,
+            seed: rand::random(),
+        }
+    }
+
+    /// Set the maximum number of iterations
+    ///
+    /// Lloyd's algorithm stops early once no vector changes cluster. Defaults to 25.
+    pub fn iterations(mut self, iterations: usize) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Update the centroids from random batches of `batch` vectors in each iteration
+    ///
+    /// If `None` (the default), every iteration uses all vectors. Mini-batch updates converge to
+    /// slightly worse clusters, but make each iteration much cheaper for large sets of vectors.
+    pub fn mini_batch(mut self, batch: Option<usize>) -> Self {
+        self.batch = batch;
+        self
+    }
+
+    /// Set the seed for the random number generator, used to choose the initial centroids
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Cluster `vectors`, which must all have the same length
+    ///
+    /// If there are fewer than `k` vectors, each vector gets its own cluster.
+    pub fn fit<V: AsRef<[f32]> + Sync>(&self, vectors: &[V]) -> Clusters {
+        assert!(self.k > 0, "at least one cluster is required");
+        assert!(!vectors.is_empty(), "no vectors to cluster");
+        let dimensions = vectors[0].as_ref().len();
+        assert!(dimensions > 0, "vectors must not be empty");
+        assert!(
+            vectors.iter().all(|v| v.as_ref().len() == dimensions),
+            "vectors must have the same length"
+        );
+
+        let mut rng = SmallRng::seed_from_u64(self.seed);
+        let k = Ord::min(self.k, vectors.len());
+        let mut centroids = initialize(vectors, k, &mut rng);
+        let (mut assignments, mut converged) = (Vec::new(), false);
+        match self.batch {
+            None => {
+                for _ in 0..self.iterations {
+                    let assigned = assign(vectors, &centroids, dimensions, self.seed);
+                    if assigned == assignments {
+                        converged = true;
+                        break;
+                    }
+
+                    assignments = assigned;
+                    update(vectors, &assignments, &mut centroids, dimensions);
+                }
+            }
+            Some(batch) => {
+                let batch = batch.clamp(1, vectors.len());
+                let mut counts = vec![0usize; k];
+                for _ in 0..self.iterations {
+                    let sample = index::sample(&mut rng, vectors.len(), batch).into_vec();
+                    let batch = sample.iter().map(|&i| &vectors[i]).collect::<Vec<_>>();
+                    let assigned = assign(&batch, &centroids, dimensions, self.seed);
+
+                    // Move each centroid towards its vectors, by less as it has seen more of them
+                    for (vector, cluster) in batch.iter().zip(assigned) {
+                        counts[cluster] += 1;
+                        let rate = 1.0 / counts[cluster] as f32;
+                        let centroid = &mut centroids[cluster * dimensions..][..dimensions];
+                        let values = centroid.iter_mut().zip(vector.as_ref());
+                        values.for_each(|(c, x)| *c += rate * (x - *c));
+                    }
+                }
+            }
+        }
+
+        // Unless Lloyd's algorithm converged, the centroids moved since vectors were last assigned
+        if !converged {
+            assignments = assign(vectors, &centroids, dimensions, self.seed);
+        }
+
+        Clusters {
+            dimensions,
+            centroids,
+            assignments,
+        }
+    }
+}
+
+/// The result of k-means clustering
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug)]
+pub struct Clusters {
+    dimensions: usize,
+    centroids: Vec<f32>,
+    assignments: Vec<usize>,
+}
+
+impl Clusters {
+    /// The centroid of each cluster
+    pub fn centroids(&self) -> impl ExactSizeIterator<Item = &[f32]> + '_ {
+        self.centroids.chunks_exact(self.dimensions)
+    }
+
+    /// The centroid of cluster `cluster`
+    pub fn centroid(&self, cluster: usize) -> &[f32] {
+        &self.centroids[cluster * self.dimensions..][..self.dimensions]
+    }
+
+    /// The cluster of each vector, in the order of the clustered vectors
+    pub fn assignments(&self) -> &[usize] {
+        &self.assignments
+    }
+
+    /// The cluster whose centroid is nearest to `vector`, comparing it to every centroid
+    pub fn assign(&self, vector: &[f32]) -> usize {
+        assert_eq!(vector.len(), self.dimensions, "vector length mismatch");
+        nearest(&self.centroids, vector)
+    }
+
+    /// The sum of squared distances from the vectors to the centroids of their clusters
+    pub fn inertia<V: AsRef<[f32]> + Sync>(&self, vectors: &[V]) -> f32 {
+        vectors
+            .par_iter()
+            .zip(&self.assignments)
+            .map(|(vector, &cluster)| simd::l2_squared(vector.as_ref(), self.centroid(cluster)))
+            .sum()
+    }
+
+    /// The number of clusters
+    pub fn len(&self) -> usize {
+        self.centroids.len() / self.dimensions.max(1)
+    }
+
+    /// Whether there are no clusters
+    pub fn is_empty(&self) -> bool {
+        self.centroids.is_empty()
+    }
+}
+
+/// Choose `k` initial centroids from `vectors` using k-means++
+///
+/// Each centroid after the first is chosen with probability proportional to the squared distance
+/// from a vector to the nearest centroid chosen so far.
+fn initialize<V: AsRef<[f32]> + Sync>(vectors: &[V], k: usize, rng: &mut SmallRng) -> Vec<f32> {
+    let first = vectors[rng.random_range(0..vectors.len())].as_ref();
+    let mut centroids = first.to_vec();
+    let mut distances = vectors
+        .par_iter()
+        .map(|v| simd::l2_squared(v.as_ref(), first))
+        .collect::<Vec<_>>();
+
+    for _ in 1..k {
+        let total = distances.iter().map(|&d| f64::from(d)).sum::<f64>();
+        let chosen = match total > 0.0 {
+            true => {
+                let mut target = rng.random::<f64>() * total;
+                let position = distances.iter().position(|&d| {
+                    target -= f64::from(d);
+                    target < 0.0
+                });
+                // Rounding may leave a small remainder after the last vector
+                position.unwrap_or_else(|| distances.iter().rposition(|&d| d > 0.0).unwrap())
+            }
+            // All vectors coincide with a centroid, so any choice is as good as another
+            false => rng.random_range(0..vectors.len()),
+        };
+
+        let centroid = vectors[chosen].as_ref();
+        centroids.extend_from_slice(centroid);
+        distances
+            .par_iter_mut()
+            .zip(vectors)
+            .for_each(|(d, v)| *d = d.min(simd::l2_squared(v.as_ref(), centroid)));
+    }
+
+    centroids
+}
+
+/// The cluster with the nearest centroid for each of `vectors`
+///
+/// With more than `INDEXED_CLUSTERS` centroids, the nearest centroids are found by searching an
+/// `Hnsw` over the centroids.
+fn assign<V: AsRef<[f32]> + Sync>(
+    vectors: &[V],
+    centroids: &[f32],
+    dimensions: usize,
+    seed: u64,
+) -> Vec<usize> {
+    if centroids.len() / dimensions.max(1) <= INDEXED_CLUSTERS {
+        return vectors
+            .par_iter()
+            .map(|vector| nearest(centroids, vector.as_ref()))
+            .collect();
+    }
+
+    let store = Mapped::new(Flat::new(dimensions, centroids));
+    let (hnsw, _) = Builder::default()
+        .seed(seed)
+        .build_hnsw_with_store(store, SquaredEuclidean);
+    vectors
+        .par_iter()
+        .map_init(Search::default, |search, vector| {
+            let item = hnsw.search(vector.as_ref(), search).next().unwrap();
+            hnsw.store().id(item.pid).into_inner() as usize
+        })
+        .collect()
+}
+
+/// Move each centroid to the mean of the vectors assigned to it
+///
+/// Clusters without any vectors keep their previous centroid.
+fn update<V: AsRef<[f32]>>(
+    vectors: &[V],
+    assignments: &[usize],
+    centroids: &mut [f32],
+    dimensions: usize,
+) {
+    let mut sums = vec![0.0f64; centroids.len()];
+    let mut counts = vec![0usize; centroids.len() / dimensions.max(1)];
+    for (vector, &cluster) in vectors.iter().zip(assignments) {
+        counts[cluster] += 1;
+        let sum = &mut sums[cluster * dimensions..][..dimensions];
+        let values = sum.iter_mut().zip(vector.as_ref());
+        values.for_each(|(s, &x)| *s += f64::from(x));
+    }
+
+    let clusters = centroids
+        .chunks_exact_mut(dimensions)
+        .zip(sums.chunks_exact(dimensions));
+    for ((centroid, sum), &count) in clusters.zip(&counts) {
+        if count > 0 {
+            let mean = sum.iter().map(|&s| (s / count as f64) as f32);
+            centroid.iter_mut().zip(mean).for_each(|(c, m)| *c = m);
+        }
+    }
+}
+
+/// The index of the centroid in `centroids` nearest to `vector`
+pub(crate) fn nearest(centroids: &[f32], vector: &[f32]) -> usize {
+    centroids
+        .chunks_exact(vector.len())
+        .map(|centroid| simd::l2_squared(centroid, vector))
+        .enumerate()
+        .fold((0, f32::INFINITY), |best, (i, distance)| {
+            match distance < best.1 {
+                true => (i, distance),
+                false => best,
+            }
+        })
+        .0
+}
+
+/// The number of clusters above which vectors are assigned by searching an `Hnsw`
+const INDEXED_CLUSTERS: usize = 64;
@@ -1,29 +1,58 @@
-use std::cmp::{max, Ordering, Reverse};
+use std::cmp::{max, Reverse};
 use std::collections::BinaryHeap;
 use std::collections::HashSet;
+use std::marker::PhantomData;
+use std::mem;
 #[cfg(feature = "indicatif")]
 use std::sync::atomic::{self, AtomicUsize};
+use std::sync::Arc;
 
 #[cfg(feature = "indicatif")]
 use indicatif::ProgressBar;
 use ordered_float::OrderedFloat;
 use parking_lot::{Mutex, RwLock};
 use rand::rngs::SmallRng;
+use rand::seq::index;
 use rand::{Rng, SeedableRng};
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
+pub mod binary;
+pub mod geo;
+pub mod kmeans;
+pub mod metric;
+pub mod mips;
+use metric::{Metric, PointMetric};
+pub mod pq;
+pub mod quantize;
+mod reorder;
+pub use reorder::Reorder;
+mod rerank;
+pub use rerank::PointSource;
+pub mod set;
+pub mod simd;
+pub mod sparse;
+pub mod store;
+pub mod text;
+pub mod transform;
+use store::{Permute, PointStore};
 mod types;
-pub use types::PointId;
-use types::{Candidate, Layer, LayerId, UpperNode, Visited, ZeroNode, INVALID};
+pub use types::{Candidate, PointId};
+use types::{Layer, LayerId, UpperNode, Visited, ZeroNode, INVALID};
+mod vamana;
+pub use vamana::Vamana;
+pub mod vector;
 
 #[derive(Clone)]
 /// Parameters for building the `Hnsw`
 pub struct Builder {
     ef_search: usize,
     ef_construction: usize,
-    heuristic: Option<Heuristic>,
+    selector: Arc<dyn NeighborSelector>,
+    vamana: Option<Vamana>,
+    entry_point: Option<EntryPoint>,
+    reorder: Option<Reorder>,
     ml: f32,
     seed: u64,
     #[cfg(feature = "indicatif")]
@@ -46,8 +75,45 @@ impl Builder {
         self
     }
 
-    pub fn select_heuristic(mut self, params: Option<Heuristic>) -> Self {
-        self.heuristic = params;
+    /// Select neighbors using the heuristic from the paper, or the simple algorithm if `None`
+    pub fn select_heuristic(self, params: Option<Heuristic>) -> Self {
+        match params {
+            Some(heuristic) => self.neighbor_selector(heuristic),
+            None => self.neighbor_selector(Simple),
+        }
+    }
+
+    /// Set the strategy used to select the neighbors of each node during construction
+    ///
+    /// Defaults to `Heuristic::default()`.
+    pub fn neighbor_selector(mut self, selector: impl NeighborSelector + 'static) -> Self {
+        self.selector = Arc::new(selector);
+        self
+    }
+
+    /// Build a flat graph as described in the DiskANN paper instead of a hierarchy of layers
+    ///
+    /// See `Vamana` for details. If `None` (the default), a hierarchical graph is built.
+    pub fn vamana(mut self, params: Option<Vamana>) -> Self {
+        self.vamana = params;
+        self
+    }
+
+    /// Set the point used as the entry point for construction and searches
+    ///
+    /// If `None` (the default), the entry point is a random point for hierarchical graphs and the
+    /// approximate medoid of all points for flat graphs. Building the index panics if an
+    /// `EntryPoint::Point` is out of range for the points passed to the builder.
+    pub fn entry_point(mut self, entry: Option<EntryPoint>) -> Self {
+        self.entry_point = entry;
+        self
+    }
+
+    /// Renumber the points after construction to improve memory locality during searches
+    ///
+    /// See `Reorder` for details. If `None` (the default), points are left in random order.
+    pub fn reorder(mut self, strategy: Option<Reorder>) -> Self {
+        self.reorder = strategy;
         self
     }
 
@@ -76,12 +142,71 @@ impl Builder {
 
     /// Build an `HnswMap` with the given sets of points and values
     pub fn build<P: Point, V: Clone>(self, points: Vec<P>, values: Vec<V>) -> HnswMap<P, V> {
-        HnswMap::new(points, values, self)
+        HnswMap::new(points, values, PointMetric, self)
     }
 
     /// Build the `Hnsw` with the given set of points
     pub fn build_hnsw<P: Point>(self, points: Vec<P>) -> (Hnsw<P>, Vec<PointId>) {
-        Hnsw::new(points, self)
+        Hnsw::new(points, PointMetric, self)
+    }
+
+    /// Build an `HnswMap` with the given sets of points and values, compared using `metric`
+    pub fn build_with_metric<P, V, D>(
+        self,
+        points: Vec<P>,
+        values: Vec<V>,
+        metric: D,
+    ) -> HnswMap<P, V, D>
+    where
+        P: Sync,
+        V: Clone,
+        D: Metric<P>,
+    {
+        HnswMap::new(points, values, metric, self)
+    }
+
+    /// Build the `Hnsw` with the given set of points, compared using `metric`
+    pub fn build_hnsw_with_metric<P, D>(
+        self,
+        points: Vec<P>,
+        metric: D,
+    ) -> (Hnsw<P, D>, Vec<PointId>)
+    where
+        P: Sync,
+        D: Metric<P>,
+    {
+        Hnsw::new(points, metric, self)
+    }
+
+    /// Build an `HnswMap` with the points in `store` and the given values, compared using `metric`
+    pub fn build_with_store<S, V, D>(
+        self,
+        store: S,
+        values: Vec<V>,
+        metric: D,
+    ) -> HnswMap<S::Point, V, D, S>
+    where
+        S: Permute,
+        V: Clone,
+        D: Metric<S::Point>,
+    {
+        HnswMap::new(store, values, metric, self)
+    }
+
+    /// Build the `Hnsw` with the points in `store`, compared using `metric`
+    ///
+    /// The points are reordered within the store during construction. Wrap the store in
+    /// `store::Mapped` to keep its points in place.
+    pub fn build_hnsw_with_store<S, D>(
+        self,
+        store: S,
+        metric: D,
+    ) -> (Hnsw<S::Point, D, S>, Vec<PointId>)
+    where
+        S: Permute,
+        D: Metric<S::Point>,
+    {
+        Hnsw::new(store, metric, self)
     }
 
     #[doc(hidden)]
@@ -89,7 +214,10 @@ impl Builder {
         let Self {
             ef_search,
             ef_construction,
-            heuristic: _,
+            selector: _,
+            vamana: _,
+            entry_point: _,
+            reorder: _,
             ml,
             seed,
             ..
@@ -103,7 +231,10 @@ impl Default for Builder {
         Self {
             ef_search: 100,
             ef_construction: 100,
-            heuristic: Some(Heuristic::default()),
+            selector: Arc::new(Heuristic::default()),
+            vamana: None,
+            entry_point: None,
+            reorder: None,
             ml: 1.0 / (M as f32).ln(),
             seed: rand::random(),
             #[cfg(feature = "indicatif")]
@@ -112,10 +243,127 @@ impl Default for Builder {
     }
 }
 
+/// Choice of the point where construction and searches enter the graph
+///
+/// The entry point is always stored as `PointId(0)` in the resulting index.
+#[derive(Copy, Clone, Debug)]
+pub enum EntryPoint {
+    /// An approximate medoid of the points in the top layer
+    Medoid,
+    /// The point at the given index in the `points` passed to the builder
+    ///
+    /// Building the index panics if the index is out of range.
+    Point(usize),
+}
+
+/// Strategy for selecting the neighbors of a node during construction
+///
+/// This is called both to pick the neighbors for a newly inserted node and to re-select the
+/// neighbors of an existing node after the new node has been proposed as one of its neighbors.
+/// `Simple` and `Heuristic` implement algorithms 3 and 4 from the paper.
+pub trait NeighborSelector: Send + Sync {
+    /// Select at most `max` neighbors from `candidates`, pushing them onto `selected`
+    ///
+    /// `candidates` is sorted by distance to the base point (nearest first) and may be used
+    /// as scratch space. `selected` is empty when this is called; its order determines the
+    /// order of the node's neighbor list, which should generally be nearest first. Selecting the
+    /// base point, a point more than once or a point not in the graph is not an error, but these
+    /// are dropped, as are the selected points beyond the first `max`.
+    fn select(
+        &self,
+        candidates: &mut Vec<Candidate>,
+        max: usize,
+        graph: &mut dyn Graph,
+        selected: &mut Vec<Candidate>,
+    );
+}
+
+/// Access to the graph under construction for `NeighborSelector` implementations
+pub trait Graph {
+    /// The base point, for which neighbors are being selected
+    fn base(&self) -> PointId;
+
+    /// Distance between the points identified by `a` and `b`
+    fn distance(&self, a: PointId, b: PointId) -> f32;
+
+    /// Push the current neighbors of `pid` onto `out` as candidates for the base point
+    ///
+    /// Points that have already been considered for the current base point are skipped.
+    fn extend(&mut self, pid: PointId, out: &mut Vec<Candidate>);
+}
+
+/// Selects the nearest candidates (algorithm 3 from the paper)
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Simple;
+
+impl NeighborSelector for Simple {
+    fn select(
+        &self,
+        candidates: &mut Vec<Candidate>,
+        max: usize,
+        _: &mut dyn Graph,
+        selected: &mut Vec<Candidate>,
+    ) {
+        selected.extend(candidates.iter().take(max));
+    }
+}
+
+/// Selects diverse neighbors (algorithm 4 from the paper)
 #[derive(Copy, Clone, Debug)]
 pub struct Heuristic {
     pub extend_candidates: bool,
     pub keep_pruned: bool,
+    /// Relaxation factor for pruning candidates (`alpha` from the DiskANN paper)
+    ///
+    /// A candidate is pruned if `alpha` times its distance to an already selected neighbor is
+    /// smaller than its distance to the base point. Values above 1.0 prune less aggressively,
+    /// keeping more long-range links; 1.0 matches the heuristic from the HNSW paper.
+    pub alpha: f32,
+}
+
+impl NeighborSelector for Heuristic {
+    fn select(
+        &self,
+        candidates: &mut Vec<Candidate>,
+        max: usize,
+        graph: &mut dyn Graph,
+        selected: &mut Vec<Candidate>,
+    ) {
+        // `candidates` will represent `W` from the paper's algorithm 4 for now.
+        if self.extend_candidates {
+            for i in 0..candidates.len() {
+                let pid = candidates[i].pid;
+                graph.extend(pid, candidates);
+            }
+            candidates.sort_unstable();
+        }
+
+        // Move the selected candidates to `selected` (`R`), leaving the discarded candidates
+        // (`Wd`) in `candidates`.
+        candidates.retain(|candidate| {
+            if selected.len() >= max {
+                return false;
+            }
+
+            // Disadvantage candidates which are closer to an existing result point than they
+            // are to the query point, to facilitate bridging between clustered points.
+            let nearest = !selected.iter().any(|result| {
+                let distance = graph.distance(candidate.pid, result.pid);
+                OrderedFloat::from(self.alpha * distance) < candidate.distance
+            });
+
+            if nearest {
+                selected.push(*candidate);
+            }
+            !nearest
+        });
+
+        if self.keep_pruned {
+            // Add discarded connections from `candidates` (`Wd`) to `selected` (`R`)
+            let room = max.saturating_sub(selected.len());
+            selected.extend(candidates.iter().take(room));
+        }
+    }
 }
 
 impl Default for Heuristic {
@@ -123,26 +371,39 @@ impl Default for Heuristic {
         Heuristic {
             extend_candidates: false,
             keep_pruned: true,
+            alpha: 1.0,
         }
     }
 }
 
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-pub struct HnswMap<P, V> {
-    hnsw: Hnsw<P>,
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "Hnsw<P, D, S>: Serialize, V: Serialize",
+        deserialize = "Hnsw<P, D, S>: Deserialize<'de>, V: Deserialize<'de>"
+    ))
+)]
+pub struct HnswMap<P: ?Sized, V, D = PointMetric, S = Vec<P>> {
+    hnsw: Hnsw<P, D, S>,
     pub values: Vec<V>,
 }
 
-impl<P, V> HnswMap<P, V>
+impl<P, V, D, S> HnswMap<P, V, D, S>
 where
-    P: Point,
+    P: ?Sized,
     V: Clone,
+    D: Metric<P>,
+    S: PointStore<Point = P>,
 {
-    fn new(points: Vec<P>, values: Vec<V>, builder: Builder) -> Self {
-        let (hnsw, ids) = Hnsw::new(points, builder);
+    fn new(points: S, values: Vec<V>, metric: D, builder: Builder) -> Self
+    where
+        S: Permute,
+    {
+        let (hnsw, ids) = Hnsw::new(points, metric, builder);
 
         let mut sorted = ids.into_iter().enumerate().collect::<Vec<_>>();
-        sorted.sort_unstable_by(|a, b| a.1.cmp(&b.1));
+        sorted.sort_unstable_by_key(|a| a.1);
         let new = sorted
             .into_iter()
             .map(|(src, _)| values[src].clone())
@@ -151,13 +412,27 @@ where
         Self { hnsw, values: new }
     }
 
-    pub fn search<'a>(
+    pub fn search<'a, Q: Query<P, D> + ?Sized>(
         &'a self,
-        point: &P,
+        query: &Q,
         search: &'a mut Search,
     ) -> impl ExactSizeIterator<Item = MapItem<'a, P, V>> + 'a {
         self.hnsw
-            .search(point, search)
+            .search(query, search)
+            .map(move |item| MapItem::from(item, self))
+    }
+
+    /// Search the index for the points nearest to `query`, starting from the given `entries`
+    ///
+    /// See `Hnsw::search_from()` for details.
+    pub fn search_from<'a, Q: Query<P, D> + ?Sized>(
+        &'a self,
+        query: &Q,
+        entries: &[PointId],
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = MapItem<'a, P, V>> + 'a {
+        self.hnsw
+            .search_from(query, entries, search)
             .map(move |item| MapItem::from(item, self))
     }
 
@@ -166,21 +441,31 @@ where
         self.hnsw.iter()
     }
 
+    /// The metric used to compare points in this index
+    pub fn metric(&self) -> &D {
+        self.hnsw.metric()
+    }
+
+    /// The store containing the points in this index
+    pub fn store(&self) -> &S {
+        self.hnsw.store()
+    }
+
     #[doc(hidden)]
     pub fn get(&self, i: usize, search: &Search) -> Option<MapItem<'_, P, V>> {
         Some(MapItem::from(self.hnsw.get(i, search)?, self))
     }
 }
 
-pub struct MapItem<'a, P, V> {
+pub struct MapItem<'a, P: ?Sized, V> {
     pub distance: f32,
     pub pid: PointId,
     pub point: &'a P,
     pub value: &'a V,
 }
 
-impl<'a, P, V> MapItem<'a, P, V> {
-    fn from(item: Item<'a, P>, map: &'a HnswMap<P, V>) -> Self {
+impl<'a, P: ?Sized, V> MapItem<'a, P, V> {
+    fn from<D, S>(item: Item<'a, P>, map: &'a HnswMap<P, V, D, S>) -> Self {
         MapItem {
             distance: item.distance,
             pid: item.pid,
@@ -191,26 +476,105 @@ impl<'a, P, V> MapItem<'a, P, V> {
 }
 
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
-pub struct Hnsw<P> {
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "Stored<D, S>",
+        bound(deserialize = "D: serde::de::DeserializeOwned, S: Deserialize<'de>")
+    )
+)]
+pub struct Hnsw<P: ?Sized, D = PointMetric, S = Vec<P>> {
     ef_search: usize,
-    points: Vec<P>,
+    points: S,
     zero: Vec<ZeroNode>,
     layers: Vec<Vec<UpperNode>>,
+    metric: D,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    point: PhantomData<P>,
 }
 
-impl<P> Hnsw<P>
+/// Serialized representation of an `Hnsw`
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+#[serde(bound(deserialize = "D: Deserialize<'de>, S: Deserialize<'de>"))]
+struct Stored<D, S> {
+    ef_search: usize,
+    points: S,
+    zero: Vec<ZeroNode>,
+    layers: Vec<Vec<UpperNode>>,
+    /// Missing for indexes serialized as JSON or other self-describing formats before the metric
+    /// was stored, which all use `PointMetric`
+    #[serde(default, deserialize_with = "present")]
+    metric: Option<D>,
+}
+
+#[cfg(feature = "serde")]
+fn present<'de, T: Deserialize<'de>, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+#[cfg(feature = "serde")]
+impl<P: ?Sized, D: serde::de::DeserializeOwned, S> TryFrom<Stored<D, S>> for Hnsw<P, D, S> {
+    type Error = serde::de::value::Error;
+
+    fn try_from(stored: Stored<D, S>) -> Result<Self, Self::Error> {
+        use serde::de::{Error, IntoDeserializer};
+
+        // Read a missing metric as the unit `PointMetric`, which fails for most other metrics
+        let metric = match stored.metric {
+            Some(metric) => metric,
+            None => D::deserialize(().into_deserializer())
+                .map_err(|_: Self::Error| Error::missing_field("metric"))?,
+        };
+
+        Ok(Self {
+            ef_search: stored.ef_search,
+            points: stored.points,
+            zero: stored.zero,
+            layers: stored.layers,
+            metric,
+            point: PhantomData,
+        })
+    }
+}
+
+impl<P, D, S> Hnsw<P, D, S>
 where
-    P: Point,
+    P: ?Sized,
+    D: Metric<P>,
+    S: PointStore<Point = P>,
 {
     pub fn builder() -> Builder {
         Builder::default()
     }
 
-    fn new(points: Vec<P>, builder: Builder) -> (Self, Vec<PointId>) {
+    fn new(points: S, metric: D, builder: Builder) -> (Self, Vec<PointId>)
+    where
+        S: Permute,
+    {
+        let reorder = builder.reorder;
+        let (mut hnsw, mut out) = match builder.vamana {
+            Some(params) => vamana::build(points, metric, builder, params),
+            None => Self::hierarchical(points, metric, builder),
+        };
+
+        if let Some(strategy) = reorder {
+            hnsw.reorder(strategy, &mut out);
+        }
+
+        (hnsw, out)
+    }
+
+    fn hierarchical(mut points: S, metric: D, builder: Builder) -> (Self, Vec<PointId>)
+    where
+        S: Permute,
+    {
         let ef_search = builder.ef_search;
         let ef_construction = builder.ef_construction;
         let ml = builder.ml;
-        let heuristic = builder.heuristic;
+        let selector = builder.selector;
         let mut rng = SmallRng::seed_from_u64(builder.seed);
 
         #[cfg(feature = "indicatif")]
@@ -222,15 +586,7 @@ where
         }
 
         if points.is_empty() {
-            return (
-                Self {
-                    ef_search,
-                    zero: Vec::new(),
-                    points: Vec::new(),
-                    layers: Vec::new(),
-                },
-                Vec::new(),
-            );
+            return (Self::empty(ef_search, points, metric), Vec::new());
         }
 
         // Determine the number and size of layers.
@@ -259,15 +615,29 @@ where
             .collect::<Vec<_>>();
         shuffled.sort_unstable();
 
+        // Move the configured entry point to the front. The nodes on the top layer are at the
+        // front of the list, so the entry point will be part of every layer.
+
+        let entry = match builder.entry_point {
+            Some(EntryPoint::Medoid) => {
+                let top_size = sizes[0].0;
+                let point = |i: usize| points.point(PointId(shuffled[i].1 as u32));
+                let distance = |i: usize, j: usize| metric.distance(point(i), point(j));
+                approximate_medoid(top_size, distance, &mut rng)
+            }
+            Some(EntryPoint::Point(idx)) => shuffled
+                .iter()
+                .position(|s| s.1 == idx)
+                .expect("entry point out of range"),
+            None => 0,
+        };
+        shuffled.swap(0, entry);
+
         let mut out = vec![INVALID; points.len()];
-        let points = shuffled
-            .into_iter()
-            .enumerate()
-            .map(|(i, (_, idx))| {
-                out[idx] = PointId(i as u32);
-                points[idx].clone()
-            })
-            .collect::<Vec<_>>();
+        for (i, (_, idx)) in shuffled.into_iter().enumerate() {
+            out[idx] = PointId(i as u32);
+        }
+        permute(&mut points, &out);
 
         // Figure out how many nodes will go on each layer. This helps us allocate memory capacity
         // for each layer in advance, and also helps enable batch insertion of points.
@@ -283,8 +653,7 @@ where
         // Initialize data for layers
 
         let mut layers = vec![vec![]; top.0];
-        let zero = points
-            .iter()
+        let zero = (0..points.len())
             .map(|_| RwLock::new(ZeroNode::default()))
             .collect::<Vec<_>>();
 
@@ -292,8 +661,11 @@ where
             zero: zero.as_slice(),
             pool: SearchPool::new(points.len()),
             top,
-            points: &points,
-            heuristic,
+            space: Space {
+                points: &points,
+                metric: &metric,
+            },
+            selector: &*selector,
             ef_construction,
             #[cfg(feature = "indicatif")]
             progress,
@@ -339,85 +711,245 @@ where
                 zero: zero.into_iter().map(|node| node.into_inner()).collect(),
                 points,
                 layers,
+                metric,
+                point: PhantomData,
             },
             out,
         )
     }
 
-    /// Search the index for the points nearest to the reference point `point`
+    fn empty(ef_search: usize, points: S, metric: D) -> Self {
+        Self {
+            ef_search,
+            zero: Vec::new(),
+            points,
+            layers: Vec::new(),
+            metric,
+            point: PhantomData,
+        }
+    }
+
+    /// Search the index for the points nearest to `query`
     ///
-    /// The results are returned in the `out` parameter; the number of neighbors to search for
+    /// The query is usually a point of type `P`, but can be any type implementing `Query`. The
+    /// results are returned in the `out` parameter; the number of neighbors to search for
     /// is limited by the size of the `out` parameter, and the number of results found is returned
     /// in the return value.
-    pub fn search<'a, 'b: 'a>(
+    pub fn search<'a, 'b: 'a, Q: Query<P, D> + ?Sized>(
+        &'b self,
+        query: &Q,
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = Item<'b, P>> + 'a {
+        self.search_from(query, &[], search)
+    }
+
+    /// Search the index for the points nearest to `query`, starting from the given `entries`
+    ///
+    /// Each entry point is used starting from the highest layer that contains it. Entry points
+    /// that are not part of this index (with an id beyond the number of points) are ignored rather
+    /// than causing a panic; if there are no valid entry points, the search starts from the
+    /// index's entry point like `search()` does.
+    pub fn search_from<'a, 'b: 'a, Q: Query<P, D> + ?Sized>(
         &'b self,
-        point: &P,
+        query: &Q,
+        entries: &[PointId],
         search: &'a mut Search,
     ) -> impl ExactSizeIterator<Item = Item<'b, P>> + 'a {
+        self.search_with(query, entries, self.ef_search, search);
+        search
+            .iter()
+            .map(move |candidate| Item::new(candidate, &self.points))
+    }
+
+    /// Search the index for the `ef` points nearest to `query`, starting from the given `entries`
+    ///
+    /// The results are left in `search`.
+    fn search_with<Q: Query<P, D> + ?Sized>(
+        &self,
+        query: &Q,
+        entries: &[PointId],
+        ef: usize,
+        search: &mut Search,
+    ) {
+        let distances = QueryDistances {
+            query,
+            space: Space {
+                points: &self.points,
+                metric: &self.metric,
+            },
+        };
+
         search.reset();
-        let map = move |candidate| Item::new(candidate, self);
         if self.points.is_empty() {
-            return search.iter().map(map);
+            return;
         }
 
+        let valid = |pid: &PointId| (pid.0 as usize) < self.points.len();
+        let entries = match entries.iter().any(valid) {
+            true => entries,
+            false => &[PointId(0)],
+        };
+
+        let top = entries.iter().filter(|pid| valid(pid));
+        let top = top.map(|&pid| self.top_layer(pid)).max().unwrap();
         search.visited.reserve_capacity(self.points.len());
-        search.push(PointId(0), point, &self.points);
-        for cur in LayerId(self.layers.len()).descend() {
+        for cur in top.descend() {
             let (ef, num) = match cur.is_zero() {
-                true => (self.ef_search, M * 2),
+                true => (ef, M * 2),
                 false => (1, M),
             };
 
             search.ef = ef;
+            for &pid in entries {
+                if valid(&pid) && self.top_layer(pid) == cur {
+                    search.push(pid, &distances);
+                }
+            }
+
             match cur.0 {
-                0 => search.search(point, self.zero.as_slice(), &self.points, num),
-                l => search.search(point, self.layers[l - 1].as_slice(), &self.points, num),
+                0 => search.search(&distances, self.zero.as_slice(), num),
+                l => search.search(&distances, self.layers[l - 1].as_slice(), num),
             }
 
             if !cur.is_zero() {
                 search.cull();
             }
         }
+    }
 
-        search.iter().map(map)
+    /// The highest layer containing the node for the point `pid`
+    fn top_layer(&self, pid: PointId) -> LayerId {
+        // Layer `n` contains the nodes for the first `layers[n - 1].len()` points
+        let upper = self.layers.iter().rposition(|l| (pid.0 as usize) < l.len());
+        LayerId(upper.map_or(0, |i| i + 1))
     }
 
     /// Iterate over the keys and values in this index
     pub fn iter(&self) -> impl Iterator<Item = (PointId, &P)> {
-        self.points
-            .iter()
-            .enumerate()
-            .map(|(i, p)| (PointId(i as u32), p))
+        (0..self.points.len()).map(|i| {
+            let pid = PointId(i as u32);
+            (pid, self.points.point(pid))
+        })
+    }
+
+    /// The metric used to compare points in this index
+    pub fn metric(&self) -> &D {
+        &self.metric
+    }
+
+    /// The store containing the points in this index
+    pub fn store(&self) -> &S {
+        &self.points
     }
 
     #[doc(hidden)]
     pub fn get(&self, i: usize, search: &Search) -> Option<Item<'_, P>> {
-        Some(Item::new(search.nearest.get(i).copied()?, self))
+        Some(Item::new(search.nearest.get(i).copied()?, &self.points))
     }
 }
 
-pub struct Item<'a, P> {
+pub struct Item<'a, P: ?Sized> {
     pub distance: f32,
     pub pid: PointId,
     pub point: &'a P,
 }
 
-impl<'a, P> Item<'a, P> {
-    fn new(candidate: Candidate, hnsw: &'a Hnsw<P>) -> Self {
+impl<'a, P: ?Sized> Item<'a, P> {
+    fn new<S: PointStore<Point = P>>(candidate: Candidate, points: &'a S) -> Self {
         Self {
             distance: candidate.distance.into_inner(),
             pid: candidate.pid,
-            point: &hnsw[candidate.pid],
+            point: points.point(candidate.pid),
+        }
+    }
+}
+
+/// The points in an index, along with the metric used to compare them
+struct Space<'a, S, D> {
+    points: &'a S,
+    metric: &'a D,
+}
+
+impl<S: PointStore, D: Metric<S::Point>> Space<'_, S, D> {
+    /// Distance between `point` and the point identified by `pid`
+    fn distance(&self, point: &S::Point, pid: PointId) -> f32 {
+        self.metric.distance(point, self.points.point(pid))
+    }
+}
+
+impl<S, D> Clone for Space<'_, S, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S, D> Copy for Space<'_, S, D> {}
+
+/// Move the points in `store` so that each point `i` ends up at `new[i]`
+fn permute(store: &mut impl Permute, new: &[PointId]) {
+    let mut permutation = new.to_vec();
+    for i in 0..permutation.len() {
+        while permutation[i].0 as usize != i {
+            let j = permutation[i].0 as usize;
+            store.swap_points(PointId(i as u32), PointId(j as u32));
+            permutation.swap(i, j);
+        }
+    }
+}
+
+/// Computes distances from a query to the points in an index while searching
+trait Distances {
+    /// Distance to the point `pid`, or `None` if it is known to be larger than `bound`
+    fn distance(&self, pid: PointId, bound: f32) -> Option<f32>;
+
+    /// Distances to each of the points `pids`, written to `out`, or `None` for points known to be
+    /// further away than `bound`
+    fn distances(&self, pids: &[PointId], bound: f32, out: &mut [Option<f32>]);
+}
+
+/// Number of points passed to `Query::distances_to()` at a time
+const BATCH: usize = M * 2;
+
+/// Distances from `query` to the points in `space`
+struct QueryDistances<'a, Q: ?Sized, S, D> {
+    query: &'a Q,
+    space: Space<'a, S, D>,
+}
+
+impl<Q, S, D> Distances for QueryDistances<'_, Q, S, D>
+where
+    Q: Query<S::Point, D> + ?Sized,
+    S: PointStore,
+{
+    fn distance(&self, pid: PointId, bound: f32) -> Option<f32> {
+        let Space { points, metric } = self.space;
+        self.query
+            .distance_to_bounded(metric, points.point(pid), bound)
+    }
+
+    fn distances(&self, pids: &[PointId], bound: f32, out: &mut [Option<f32>]) {
+        let Some(&first) = pids.first() else {
+            return;
+        };
+
+        let Space { points, metric } = self.space;
+        let mut batch = [points.point(first); BATCH];
+        for (pids, out) in pids.chunks(BATCH).zip(out.chunks_mut(BATCH)) {
+            for (point, &pid) in batch.iter_mut().zip(pids) {
+                *point = points.point(pid);
+            }
+            self.query
+                .distances_to(metric, &batch[..pids.len()], bound, out);
         }
     }
 }
 
-struct Construction<'a, P: Point> {
+struct Construction<'a, S, D> {
     zero: &'a [RwLock<ZeroNode>],
     pool: SearchPool,
     top: LayerId,
-    points: &'a [P],
-    heuristic: Option<Heuristic>,
+    space: Space<'a, S, D>,
+    selector: &'a dyn NeighborSelector,
     ef_construction: usize,
     #[cfg(feature = "indicatif")]
     progress: Option<ProgressBar>,
@@ -425,7 +957,7 @@ struct Construction<'a, P: Point> {
     done: AtomicUsize,
 }
 
-impl<P: Point> Construction<'_, P> {
+impl<S: PointStore, D: Metric<S::Point>> Construction<'_, S, D> {
     /// Insert new node in the zero layer
     ///
     /// * `new` is the `PointId` for the new node
@@ -439,9 +971,13 @@ impl<P: Point> Construction<'_, P> {
         let (mut search, mut insertion) = self.pool.pop();
         insertion.ef = self.ef_construction;
 
-        let point = &self.points[new];
+        let distances = QueryDistances {
+            query: self.space.points.point(new),
+            space: self.space,
+        };
+
         search.reset();
-        search.push(PointId(0), point, self.points);
+        search.push(PointId(0), &distances);
         let num = if layer.is_zero() { M * 2 } else { M };
 
         for cur in self.top.descend() {
@@ -452,25 +988,17 @@ impl<P: Point> Construction<'_, P> {
             };
             match cur > layer {
                 true => {
-                    search.search(point, layers[cur.0 - 1].as_slice(), self.points, num);
+                    search.search(&distances, layers[cur.0 - 1].as_slice(), num);
                     search.cull();
                 }
                 false => {
-                    search.search(point, self.zero, self.points, num);
+                    search.search(&distances, self.zero, num);
                     break;
                 }
             }
         }
 
-        let found = match self.heuristic {
-            None => {
-                let candidates = search.select_simple();
-                &candidates[..Ord::min(candidates.len(), M * 2)]
-            }
-            Some(heuristic) => {
-                search.select_heuristic(&self.points[new], self.zero, self.points, heuristic)
-            }
-        };
+        let found = search.select(new, self.zero, self.space, self.selector, M * 2);
 
         // Just make sure the candidates are all unique
         debug_assert_eq!(
@@ -480,46 +1008,20 @@ impl<P: Point> Construction<'_, P> {
 
         for (i, candidate) in found.iter().enumerate() {
             // `candidate` here is the new node's neighbor
-            let &Candidate { distance, pid } = candidate;
-            if let Some(heuristic) = self.heuristic {
-                let found = insertion.add_neighbor_heuristic(
-                    new,
-                    self.zero.nearest_iter(pid),
-                    self.zero,
-                    &self.points[pid],
-                    self.points,
-                    heuristic,
-                );
-
-                self.zero[pid]
-                    .write()
-                    .rewrite(found.iter().map(|candidate| candidate.pid));
-            } else {
-                // Find the correct index to insert at to keep the neighbor's neighbors sorted
-                let old = &self.points[pid];
-                let idx = self.zero[pid]
-                    .read()
-                    .binary_search_by(|third| {
-                        // `third` here is one of the neighbors of the new node's neighbor.
-                        let third = match third {
-                            pid if pid.is_valid() => *pid,
-                            // if `third` is `None`, our new `node` is always "closer"
-                            _ => return Ordering::Greater,
-                        };
-
-                        distance.cmp(&old.distance(&self.points[third]).into())
-                    })
-                    .unwrap_or_else(|e| e);
-
-                self.zero[pid].write().insert(idx, new);
-            }
+            let pid = candidate.pid;
+            let found =
+                insertion.add_neighbor(new, pid, self.zero, self.space, self.selector, M * 2);
+
+            self.zero[pid]
+                .write()
+                .rewrite(found.iter().map(|candidate| candidate.pid));
             node.set(i, pid);
         }
 
         #[cfg(feature = "indicatif")]
         if let Some(bar) = &self.progress {
             let value = self.done.fetch_add(1, atomic::Ordering::Relaxed);
-            if value % 1000 == 0 {
+            if value.is_multiple_of(1000) {
                 bar.set_position(value as u64);
             }
         }
@@ -566,9 +1068,13 @@ pub struct Search {
     ///
     /// This must always be in sorted (nearest first) order.
     nearest: Vec<Candidate>,
-    /// Working set for heuristic selection
+    /// Working set for neighbor selection
     working: Vec<Candidate>,
-    discarded: Vec<Candidate>,
+    /// Unvisited neighbors of the current candidate, and their distances to the query
+    batch: Vec<PointId>,
+    out: Vec<Option<f32>>,
+    /// Nodes whose neighbors were visited, if these are being recorded
+    expanded: Option<Vec<Candidate>>,
     /// Maximum number of nearest neighbors to retain (`ef` in the paper)
     ef: usize,
 }
@@ -595,7 +1101,11 @@ impl Search {
     ///
     /// Invariants: `self.nearest` should be in sorted (nearest first) order, and should be
     /// truncated to `self.ef`.
-    fn search<L: Layer, P: Point>(&mut self, point: &P, layer: L, points: &[P], links: usize) {
+    ///
+    /// The unvisited neighbors of each candidate are gathered first, so that their distances can
+    /// be computed in a single batch.
+    fn search<L: Layer>(&mut self, distances: &impl Distances, layer: L, links: usize) {
+        let (mut batch, mut out) = (mem::take(&mut self.batch), mem::take(&mut self.out));
         while let Some(Reverse(candidate)) = self.candidates.pop() {
             if let Some(furthest) = self.nearest.last() {
                 if candidate.distance > furthest.distance {
@@ -603,111 +1113,124 @@ impl Search {
                 }
             }
 
-            for pid in layer.nearest_iter(candidate.pid).take(links) {
-                self.push(pid, point, points);
+            if let Some(expanded) = &mut self.expanded {
+                expanded.push(candidate);
+            }
+
+            batch.clear();
+            let neighbors = layer.nearest_iter(candidate.pid).take(links);
+            batch.extend(neighbors.filter(|&pid| self.visited.insert(pid)));
+            out.resize(batch.len(), None);
+            distances.distances(&batch, self.bound(), &mut out);
+            for (&pid, &distance) in batch.iter().zip(&out) {
+                if let Some(distance) = distance {
+                    self.insert(pid, distance);
+                }
             }
 
             // If we don't truncate here, `furthest` will be further out than necessary, making
             // us continue looping while we could have broken out.
             self.nearest.truncate(self.ef);
         }
+
+        (self.batch, self.out) = (batch, out);
     }
 
-    fn add_neighbor_heuristic<L: Layer, P: Point>(
+    /// Re-select the neighbors of `pid` after `new` was proposed as one of its neighbors
+    fn add_neighbor<L: Layer, S: PointStore, D: Metric<S::Point>>(
         &mut self,
         new: PointId,
-        current: impl Iterator<Item = PointId>,
+        pid: PointId,
         layer: L,
-        point: &P,
-        points: &[P],
-        params: Heuristic,
+        space: Space<'_, S, D>,
+        selector: &dyn NeighborSelector,
+        max: usize,
     ) -> &[Candidate] {
         self.reset();
-        self.push(new, point, points);
-        for pid in current {
-            self.push(pid, point, points);
+        let distances = QueryDistances {
+            query: space.points.point(pid),
+            space,
+        };
+
+        self.push(new, &distances);
+        for current in layer.nearest_iter(pid) {
+            self.push(current, &distances);
         }
-        self.select_heuristic(point, layer, points, params)
+        self.select(pid, layer, space, selector, max)
     }
 
-    /// Heuristically sort and truncate neighbors in `self.nearest`
+    /// Select at most `max` neighbors for the `base` point from `self.nearest`
     ///
     /// Invariant: `self.nearest` must be in sorted (nearest first) order.
-    fn select_heuristic<L: Layer, P: Point>(
+    fn select<L: Layer, S: PointStore, D: Metric<S::Point>>(
         &mut self,
-        point: &P,
+        base: PointId,
         layer: L,
-        points: &[P],
-        params: Heuristic,
+        space: Space<'_, S, D>,
+        selector: &dyn NeighborSelector,
+        max: usize,
     ) -> &[Candidate] {
         self.working.clear();
-        // Get input candidates from `self.nearest` and store them in `self.working`.
-        // `self.candidates` will represent `W` from the paper's algorithm 4 for now.
-        for &candidate in &self.nearest {
-            self.working.push(candidate);
-            if params.extend_candidates {
-                for hop in layer.nearest_iter(candidate.pid) {
-                    if !self.visited.insert(hop) {
-                        continue;
-                    }
-
-                    let other = &points[hop];
-                    let distance = OrderedFloat::from(point.distance(other));
-                    let new = Candidate { distance, pid: hop };
-                    self.working.push(new);
-                }
-            }
-        }
-
-        if params.extend_candidates {
-            self.working.sort_unstable();
-        }
-
+        self.working.extend_from_slice(&self.nearest);
         self.nearest.clear();
-        self.discarded.clear();
-        for candidate in self.working.drain(..) {
-            if self.nearest.len() >= M * 2 {
-                break;
-            }
 
-            // Disadvantage candidates which are closer to an existing result point than they
-            // are to the query point, to facilitate bridging between clustered points.
-            let candidate_point = &points[candidate.pid];
-            let nearest = !self.nearest.iter().any(|result| {
-                let distance = OrderedFloat::from(candidate_point.distance(&points[result.pid]));
-                distance < candidate.distance
-            });
+        let len = space.points.len();
+        let mut graph = SelectGraph {
+            base,
+            layer,
+            space,
+            visited: &mut self.visited,
+        };
 
-            match nearest {
-                true => self.nearest.push(candidate),
-                false => self.discarded.push(candidate),
-            }
-        }
+        selector.select(&mut self.working, max, &mut graph, &mut self.nearest);
 
-        if params.keep_pruned {
-            // Add discarded connections from `working` (`Wd`) to `self.nearest` (`R`)
-            for candidate in self.discarded.drain(..) {
-                if self.nearest.len() >= M * 2 {
-                    break;
-                }
-                self.nearest.push(candidate);
+        // Keep the first `max` selected points that the node can link to, in the selector's order
+        let mut kept = 0;
+        for i in 0..self.nearest.len() {
+            let pid = self.nearest[i].pid;
+            if kept == max {
+                break;
+            } else if pid == base
+                || pid.0 as usize >= len
+                || self.nearest[..kept].iter().any(|c| c.pid == pid)
+            {
+                continue;
             }
+
+            self.nearest[kept] = self.nearest[i];
+            kept += 1;
         }
 
+        self.nearest.truncate(kept);
         &self.nearest
     }
 
-    /// Track node `pid` as a potential new neighbor for the given `point`
+    /// Track node `pid` as a potential new neighbor for the query
     ///
-    /// Will immediately return if the node has been considered before. This implements
-    /// the inner loop from the paper's algorithm 2.
-    fn push<P: Point>(&mut self, pid: PointId, point: &P, points: &[P]) {
+    /// `distances` computes the distance from the query to a node, bounded by the distance of the
+    /// furthest neighbor retained so far. Will immediately return if the node has been considered
+    /// before. This implements the inner loop from the paper's algorithm 2.
+    fn push(&mut self, pid: PointId, distances: &impl Distances) {
         if !self.visited.insert(pid) {
             return;
         }
 
-        let other = &points[pid];
-        let distance = OrderedFloat::from(point.distance(other));
+        if let Some(distance) = distances.distance(pid, self.bound()) {
+            self.insert(pid, distance);
+        }
+    }
+
+    /// The distance beyond which nodes can't become one of the `ef` nearest neighbors
+    ///
+    /// This is the distance of the furthest retained neighbor once `ef` neighbors were found.
+    fn bound(&self) -> f32 {
+        let furthest = self.ef.checked_sub(1).and_then(|i| self.nearest.get(i));
+        furthest.map_or(f32::INFINITY, |c| c.distance.into_inner())
+    }
+
+    /// Add `pid` to the nearest neighbors if it is among the `ef` nearest so far
+    fn insert(&mut self, pid: PointId, distance: f32) {
+        let distance = OrderedFloat::from(distance);
         let new = Candidate { distance, pid };
         let idx = match self.nearest.binary_search(&new) {
             Err(idx) if idx < self.ef => idx,
@@ -743,7 +1266,9 @@ impl Search {
             candidates,
             nearest,
             working,
-            discarded,
+            batch: _,
+            out: _,
+            expanded,
             ef: _,
         } = self;
 
@@ -751,12 +1276,9 @@ impl Search {
         candidates.clear();
         nearest.clear();
         working.clear();
-        discarded.clear();
-    }
-
-    /// Selection of neighbors for insertion (algorithm 3 from the paper)
-    fn select_simple(&mut self) -> &[Candidate] {
-        &self.nearest
+        if let Some(expanded) = expanded {
+            expanded.clear();
+        }
     }
 
     fn iter(&self) -> impl ExactSizeIterator<Item = Candidate> + '_ {
@@ -771,14 +1293,134 @@ impl Default for Search {
             candidates: BinaryHeap::new(),
             nearest: Vec::new(),
             working: Vec::new(),
-            discarded: Vec::new(),
+            batch: Vec::new(),
+            out: Vec::new(),
+            expanded: None,
             ef: 1,
         }
     }
 }
 
+/// Find a point that is close to the medoid of the first `len` points
+///
+/// Picks the point from a random sample of the points that has the smallest sum of distances
+/// to all the other points in the sample, returning its index.
+fn approximate_medoid(
+    len: usize,
+    distance: impl Fn(usize, usize) -> f32,
+    rng: &mut impl Rng,
+) -> usize {
+    let sample = index::sample(rng, len, Ord::min(len, MEDOID_SAMPLE)).into_vec();
+    sample
+        .iter()
+        .map(|&i| {
+            let sum = sample.iter().map(|&j| distance(i, j)).sum::<f32>();
+            (OrderedFloat::from(sum), i)
+        })
+        .min()
+        .map(|(_, i)| i)
+        .unwrap()
+}
+
+/// The number of points sampled to find an approximate medoid
+const MEDOID_SAMPLE: usize = 256;
+
+/// The `Graph` passed to a `NeighborSelector` during construction
+struct SelectGraph<'a, L, S, D> {
+    base: PointId,
+    layer: L,
+    space: Space<'a, S, D>,
+    visited: &'a mut Visited,
+}
+
+impl<L: Layer, S: PointStore, D: Metric<S::Point>> Graph for SelectGraph<'_, L, S, D> {
+    fn base(&self) -> PointId {
+        self.base
+    }
+
+    fn distance(&self, a: PointId, b: PointId) -> f32 {
+        self.space.distance(self.space.points.point(a), b)
+    }
+
+    fn extend(&mut self, pid: PointId, out: &mut Vec<Candidate>) {
+        let point = self.space.points.point(self.base);
+        for hop in self.layer.nearest_iter(pid) {
+            if !self.visited.insert(hop) {
+                continue;
+            }
+
+            let distance = OrderedFloat::from(self.space.distance(point, hop));
+            out.push(Candidate { distance, pid: hop });
+        }
+    }
+}
+
 pub trait Point: Clone + Sync {
     fn distance(&self, other: &Self) -> f32;
+
+    /// Distance to `other`, or `None` if it is known to be larger than `bound`
+    ///
+    /// Searches call this with the distance of the furthest neighbor they retain, so that
+    /// implementations can stop computing the distance as soon as it exceeds `bound`. The result
+    /// may be larger than `bound`. The default implementation always computes the full distance.
+    fn distance_bounded(&self, other: &Self, bound: f32) -> Option<f32> {
+        let _ = bound;
+        Some(self.distance(other))
+    }
+
+    /// Distances to each of the points in `others`, written to `out`
+    ///
+    /// Searches use this to compare the query to all unvisited neighbors of a node at once, so
+    /// implementations can prefetch the points or compare several of them at a time. `out` has
+    /// the same length as `others`. Like `distance_bounded()`, implementations may write `None`
+    /// for points known to be further away than `bound`. The default implementation calls
+    /// `distance_bounded()` for each point.
+    fn distances(&self, others: &[&Self], bound: f32, out: &mut [Option<f32>]) {
+        for (other, out) in others.iter().zip(out) {
+            *out = self.distance_bounded(other, bound);
+        }
+    }
+}
+
+/// A query for searching an index of points of type `P`, compared using the metric `D`
+///
+/// Every point is a query for indexes of its own type. Other query types can be used to search
+/// without constructing a point of type `P`: for example, a full-precision vector can be used to
+/// search an index of quantized vectors, or a query can carry precomputed data such as its norm.
+pub trait Query<P: ?Sized, D = PointMetric> {
+    /// Distance from this query to `point`, using the index's `metric`
+    fn distance_to(&self, metric: &D, point: &P) -> f32;
+
+    /// Distance from this query to `point`, or `None` if it is known to be larger than `bound`
+    ///
+    /// See `Point::distance_bounded()` for details.
+    fn distance_to_bounded(&self, metric: &D, point: &P, bound: f32) -> Option<f32> {
+        let _ = bound;
+        Some(self.distance_to(metric, point))
+    }
+
+    /// Distances from this query to each of the `points`, written to `out`
+    ///
+    /// See `Point::distances()` for details.
+    fn distances_to(&self, metric: &D, points: &[&P], bound: f32, out: &mut [Option<f32>]) {
+        for (point, out) in points.iter().zip(out) {
+            *out = self.distance_to_bounded(metric, point, bound);
+        }
+    }
+}
+
+impl<P: ?Sized, D: Metric<P>> Query<P, D> for P {
+    fn distance_to(&self, metric: &D, point: &P) -> f32 {
+        metric.distance(self, point)
+    }
+
+    fn distance_to_bounded(&self, metric: &D, point: &P, bound: f32) -> Option<f32> {
+        metric.distance_bounded(self, point, bound)
+    }
+
+    fn distances_to(&self, metric: &D, points: &[&P], bound: f32, out: &mut [Option<f32>]) {
+        metric.distances(self, points, bound, out)
+    }
 }
 
 /// The parameter `M` from the paper
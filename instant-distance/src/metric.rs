@@ -0,0 +1,253 @@
+//! Distance metrics
+//!
+//! A `Metric` compares two points. The index stores its metric alongside the points, so the same
+//! point type can be indexed with different metrics by passing one to
+//! `Builder::build_with_metric()` or `Builder::build_hnsw_with_metric()`.
+//!
+//! The metrics defined here are zero-sized types. Those for dense `f32` vectors can also be
+//! selected through a type parameter on the vector types in the `vector` module. When serialized,
+//! they are recorded by name, and deserializing an index with a different metric fails.
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{simd, Point};
+
+/// A distance metric over points of type `P`
+///
+/// Smaller distances mean the points are more similar. For dense vectors, both vectors passed to
+/// `distance()` must have the same length.
+pub trait Metric<P: ?Sized = [f32]>: Sync {
+    /// Distance between the points `a` and `b`
+    fn distance(&self, a: &P, b: &P) -> f32;
+
+    /// Distance between the points `a` and `b`, or `None` if it is known to be larger than `bound`
+    ///
+    /// See `Point::distance_bounded()` for details.
+    fn distance_bounded(&self, a: &P, b: &P, bound: f32) -> Option<f32> {
+        let _ = bound;
+        Some(self.distance(a, b))
+    }
+
+    /// Distances between `a` and each of the points in `others`, written to `out`
+    ///
+    /// See `Point::distances()` for details.
+    fn distances(&self, a: &P, others: &[&P], bound: f32, out: &mut [Option<f32>]) {
+        for (b, out) in others.iter().zip(out) {
+            *out = self.distance_bounded(a, b, bound);
+        }
+    }
+}
+
+/// Compares points using their `Point` implementation
+///
+/// This is the default metric for `Hnsw` and `HnswMap`, used by `Builder::build()` and
+/// `Builder::build_hnsw()`. Unlike the other metrics, it is serialized as a unit rather than by
+/// name, which leaves the serialized layout of indexes using it as it was before indexes stored
+/// their metric.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PointMetric;
+
+impl<P: Point> Metric<P> for PointMetric {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        a.distance(b)
+    }
+
+    fn distance_bounded(&self, a: &P, b: &P, bound: f32) -> Option<f32> {
+        a.distance_bounded(b, bound)
+    }
+
+    fn distances(&self, a: &P, others: &[&P], bound: f32, out: &mut [Option<f32>]) {
+        a.distances(others, bound, out)
+    }
+}
+
+/// Euclidean (L2) distance
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Euclidean;
+
+impl<P: AsRef<[f32]> + ?Sized> Metric<P> for Euclidean {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        simd::l2_squared(a, b).sqrt()
+    }
+
+    fn distance_bounded(&self, a: &P, b: &P, bound: f32) -> Option<f32> {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        bounded_sum(a, b, bound * bound, simd::l2_squared).map(f32::sqrt)
+    }
+}
+
+/// Squared Euclidean distance
+///
+/// This ranks neighbors in the same order as `Euclidean` but avoids the square root.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SquaredEuclidean;
+
+impl<P: AsRef<[f32]> + ?Sized> Metric<P> for SquaredEuclidean {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        simd::l2_squared(a, b)
+    }
+
+    fn distance_bounded(&self, a: &P, b: &P, bound: f32) -> Option<f32> {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        bounded_sum(a, b, bound, simd::l2_squared)
+    }
+}
+
+/// Cosine distance, defined as one minus the cosine similarity
+///
+/// Distances range from 0 (same direction) to 2 (opposite directions). A zero vector has a
+/// distance of 1 to any other vector.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Cosine;
+
+impl<P: AsRef<[f32]> + ?Sized> Metric<P> for Cosine {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        simd::cosine(a, b)
+    }
+}
+
+/// Negative inner product
+///
+/// The inner product is negated so that vectors with a larger inner product are closer. Note that
+/// this is not a metric in the mathematical sense: a vector is not necessarily closest to itself.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct InnerProduct;
+
+impl<P: AsRef<[f32]> + ?Sized> Metric<P> for InnerProduct {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        -simd::dot(a, b)
+    }
+}
+
+/// Manhattan (L1) distance
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Manhattan;
+
+impl<P: AsRef<[f32]> + ?Sized> Metric<P> for Manhattan {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        debug_assert_eq!(a.len(), b.len());
+        l1(a, b)
+    }
+
+    fn distance_bounded(&self, a: &P, b: &P, bound: f32) -> Option<f32> {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        bounded_sum(a, b, bound, l1)
+    }
+}
+
+/// Chebyshev (L-infinity) distance, the largest difference in any dimension
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Chebyshev;
+
+impl<P: AsRef<[f32]> + ?Sized> Metric<P> for Chebyshev {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        debug_assert_eq!(a.len(), b.len());
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f32::max)
+    }
+
+    fn distance_bounded(&self, a: &P, b: &P, bound: f32) -> Option<f32> {
+        let (a, b) = (a.as_ref(), b.as_ref());
+        debug_assert_eq!(a.len(), b.len());
+        let mut max = 0.0;
+        for (a, b) in a.iter().zip(b) {
+            max = f32::max(max, (a - b).abs());
+            if max > bound {
+                return None;
+            }
+        }
+        Some(max)
+    }
+}
+
+/// Hamming distance between bit vectors, the number of bits that differ
+///
+/// Bit vectors are stored as `u64` words, for example using the types from the `binary` module.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Hamming;
+
+impl<P: AsRef<[u64]> + ?Sized> Metric<P> for Hamming {
+    fn distance(&self, a: &P, b: &P) -> f32 {
+        simd::hamming(a.as_ref(), b.as_ref()) as f32
+    }
+}
+
+/// Sum `partial` over chunks of `a` and `b`, stopping as soon as the sum exceeds `bound`
+///
+/// `partial` must not return negative values. The result may differ slightly from computing
+/// `partial` over the whole vectors, due to rounding.
+fn bounded_sum(
+    a: &[f32],
+    b: &[f32],
+    bound: f32,
+    partial: impl Fn(&[f32], &[f32]) -> f32,
+) -> Option<f32> {
+    assert_eq!(a.len(), b.len(), "vector length mismatch");
+    if a.len() <= BOUNDED_CHUNK {
+        return Some(partial(a, b));
+    }
+
+    let mut sum = 0.0;
+    for (a, b) in a.chunks(BOUNDED_CHUNK).zip(b.chunks(BOUNDED_CHUNK)) {
+        sum += partial(a, b);
+        if sum > bound {
+            return None;
+        }
+    }
+    Some(sum)
+}
+
+fn l1(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(a, b)| (a - b).abs()).sum()
+}
+
+/// Number of dimensions compared between checks against the bound in `bounded_sum()`
+const BOUNDED_CHUNK: usize = 64;
+
+/// Implement serialization for zero-sized metrics, recording them by name
+macro_rules! serde_by_name {
+    ($($name:ident),*) => {$(
+        #[cfg(feature = "serde")]
+        impl Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_str(stringify!($name))
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                let name = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+                match name == stringify!($name) {
+                    true => Ok($name),
+                    false => Err(serde::de::Error::invalid_value(
+                        serde::de::Unexpected::Str(&name),
+                        &stringify!($name),
+                    )),
+                }
+            }
+        }
+    )*};
+}
+
+pub(crate) use serde_by_name;
+
+serde_by_name!(
+    Euclidean,
+    SquaredEuclidean,
+    Cosine,
+    InnerProduct,
+    Manhattan,
+    Chebyshev,
+    Hamming
+);
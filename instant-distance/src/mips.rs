@@ -0,0 +1,102 @@
+//! Maximum inner product search
+//!
+//! The inner product is not a metric, so a graph built from negative inner products prunes
+//! neighbors poorly and gives low recall. Instead, the points are augmented with one extra
+//! dimension so that they all have the same norm: for a vector `x` and the largest norm `m` among
+//! the indexed vectors, the extra value is `sqrt(m² - |x|²)`. The Euclidean distance between a
+//! query and an augmented point then grows as their inner product decreases, so the graph is built
+//! with Euclidean distances while searches find the largest inner products.
+//!
+//! A `Mips` is fitted to the vectors to index, and is the `Metric` for the augmented vectors.
+//! Searches use the original query as a `[f32]` slice, and return the negative inner product as
+//! the distance:
+//!
+//! ```
+//! use instant_distance::mips::Mips;
+//! use instant_distance::{Builder, Search};
+//!
+//! let vectors = vec![vec![1.0, 0.0], vec![0.0, 3.0], vec![2.0, 2.0]];
+//! let mips = Mips::fit(&vectors);
+//!
+//! let points = vectors.iter().map(|v| mips.augment(v)).collect();
+//! let map = Builder::default().build_with_metric(points, vec!["a", "b", "c"], mips);
+//!
+//! let query = [1.0, 0.1];
+//! let mut search = Search::default();
+//! let nearest = map.search(&query[..], &mut search).next().unwrap();
+//! assert_eq!(*nearest.value, "c");
+//! assert_eq!(nearest.distance, -2.2);
+//! ```
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::metric::{Euclidean, Metric};
+use crate::{simd, Query};
+
+/// Transform reducing maximum inner product search to nearest neighbor search
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mips {
+    /// The largest norm among the fitted vectors
+    max_norm: f32,
+}
+
+impl Mips {
+    /// Fit the transform to the vectors to be indexed
+    pub fn fit<V: AsRef<[f32]>>(vectors: &[V]) -> Self {
+        let max_norm = vectors
+            .iter()
+            .map(|v| simd::dot(v.as_ref(), v.as_ref()).sqrt())
+            .fold(0.0, f32::max);
+        Self { max_norm }
+    }
+
+    /// Augment `vector` for indexing
+    ///
+    /// Vectors with a larger norm than any of the fitted vectors get an extra value of zero, which
+    /// makes their inner products less accurate.
+    pub fn augment(&self, vector: &[f32]) -> MipsVector {
+        let squared = simd::dot(vector, vector);
+        let extra = (self.max_norm * self.max_norm - squared).max(0.0).sqrt();
+        MipsVector {
+            values: vector.iter().copied().chain([extra]).collect(),
+        }
+    }
+
+    /// The largest norm among the fitted vectors
+    pub fn max_norm(&self) -> f32 {
+        self.max_norm
+    }
+}
+
+impl Metric<MipsVector> for Mips {
+    fn distance(&self, a: &MipsVector, b: &MipsVector) -> f32 {
+        Euclidean.distance(&a.values[..], &b.values[..])
+    }
+
+    fn distance_bounded(&self, a: &MipsVector, b: &MipsVector, bound: f32) -> Option<f32> {
+        Euclidean.distance_bounded(&a.values[..], &b.values[..], bound)
+    }
+}
+
+impl Query<MipsVector, Mips> for [f32] {
+    fn distance_to(&self, _: &Mips, point: &MipsVector) -> f32 {
+        -simd::dot(self, point.vector())
+    }
+}
+
+/// A vector augmented by `Mips`
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MipsVector {
+    /// The original values, followed by the extra value
+    values: Box<[f32]>,
+}
+
+impl MipsVector {
+    /// The original vector, without the extra value
+    pub fn vector(&self) -> &[f32] {
+        &self.values[..self.values.len() - 1]
+    }
+}
@@ -0,0 +1,263 @@
+//! Product quantization of dense `f32` vectors
+//!
+//! A `ProductQuantizer` splits vectors into a number of subspaces and trains a codebook of up to
+//! 256 centroids for each subspace using k-means. Each vector is then encoded as the index of the
+//! nearest centroid in each subspace, using one byte per subspace.
+//!
+//! The quantizer is the `Metric` for the encoded vectors, comparing them through their
+//! centroids. Searches are best done with `Hnsw::search_asymmetric()`, which compares the
+//! full-precision query to the encoded points using a lookup table computed once per query.
+//! The `DistanceTable` can also be passed directly to `Hnsw::search()` as the `Query`.
+
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::kmeans::{self, KMeans};
+use crate::metric::{Euclidean, InnerProduct, Manhattan, Metric, SquaredEuclidean};
+use crate::{simd, Hnsw, HnswMap, Item, MapItem, Query, Search};
+
+/// Trained codebooks for encoding vectors with product quantization
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug)]
+pub struct ProductQuantizer<M = Euclidean> {
+    dimensions: usize,
+    subspaces: usize,
+    centroids: usize,
+    /// The centroids for each subspace, stored contiguously
+    codebooks: Vec<f32>,
+    metric: M,
+}
+
+impl<M: Default> ProductQuantizer<M> {
+    /// Train a quantizer with the given number of `subspaces` for vectors similar to `vectors`
+    ///
+    /// The vector length must be a multiple of `subspaces`. Each codebook contains 256 centroids,
+    /// or one centroid per vector if there are fewer vectors. `seed` is used to initialize k-means.
+    pub fn train<V: AsRef<[f32]> + Sync>(vectors: &[V], subspaces: usize, seed: u64) -> Self {
+        assert!(!vectors.is_empty(), "no training vectors");
+        let dimensions = vectors[0].as_ref().len();
+        assert!(
+            subspaces > 0 && dimensions % subspaces == 0,
+            "vector length must be a multiple of the number of subspaces"
+        );
+
+        let centroids = Ord::min(vectors.len(), CENTROIDS);
+        let width = dimensions / subspaces;
+        let codebooks = (0..subspaces)
+            .into_par_iter()
+            .map(|subspace| {
+                let range = subspace * width..(subspace + 1) * width;
+                let data = vectors
+                    .iter()
+                    .map(|vector| {
+                        let vector = vector.as_ref();
+                        assert_eq!(
+                            vector.len(),
+                            dimensions,
+                            "vectors must have the same length"
+                        );
+                        &vector[range.clone()]
+                    })
+                    .collect::<Vec<_>>();
+
+                let clusters = KMeans::new(centroids)
+                    .seed(seed.wrapping_add(subspace as u64))
+                    .fit(&data);
+                clusters.centroids().flatten().copied().collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+            .concat();
+
+        Self {
+            dimensions,
+            subspaces,
+            centroids,
+            codebooks,
+            metric: M::default(),
+        }
+    }
+}
+
+impl<M> ProductQuantizer<M> {
+    /// Encode `vector` as the nearest centroid for each subspace
+    pub fn encode(&self, vector: &[f32]) -> PqCode {
+        assert_eq!(vector.len(), self.dimensions, "vector length mismatch");
+        let codes = vector
+            .chunks_exact(self.width())
+            .enumerate()
+            .map(|(subspace, sub)| kmeans::nearest(self.codebook(subspace), sub) as u8)
+            .collect();
+        PqCode { codes }
+    }
+
+    /// Decode `code` into an approximation of the original vector
+    pub fn decode(&self, code: &PqCode) -> Vec<f32> {
+        let mut out = Vec::with_capacity(self.dimensions);
+        for (subspace, &code) in code.codes.iter().enumerate() {
+            out.extend_from_slice(self.centroid(subspace, code));
+        }
+        out
+    }
+
+    fn centroid(&self, subspace: usize, code: u8) -> &[f32] {
+        let width = self.width();
+        let start = (subspace * self.centroids + code as usize) * width;
+        &self.codebooks[start..start + width]
+    }
+
+    fn codebook(&self, subspace: usize) -> &[f32] {
+        let len = self.centroids * self.width();
+        &self.codebooks[subspace * len..(subspace + 1) * len]
+    }
+
+    fn width(&self) -> usize {
+        self.dimensions / self.subspaces
+    }
+}
+
+impl<M: SubspaceMetric> ProductQuantizer<M> {
+    /// Compute the table of distances from `query` to each of the centroids
+    pub fn table(&self, query: &[f32]) -> DistanceTable<'_, M> {
+        assert_eq!(query.len(), self.dimensions, "vector length mismatch");
+        let mut table = Vec::with_capacity(self.subspaces * self.centroids);
+        for (subspace, sub) in query.chunks_exact(self.width()).enumerate() {
+            let codebook = self.codebook(subspace).chunks_exact(self.width());
+            table.extend(codebook.map(|centroid| self.metric.partial(sub, centroid)));
+        }
+
+        DistanceTable {
+            quantizer: self,
+            table,
+        }
+    }
+}
+
+impl<M: SubspaceMetric> Metric<PqCode> for ProductQuantizer<M> {
+    fn distance(&self, a: &PqCode, b: &PqCode) -> f32 {
+        let sum = (0..self.subspaces)
+            .map(|subspace| {
+                let (a, b) = (a.codes[subspace], b.codes[subspace]);
+                let (a, b) = (self.centroid(subspace, a), self.centroid(subspace, b));
+                self.metric.partial(a, b)
+            })
+            .sum();
+        self.metric.combine(sum)
+    }
+}
+
+/// Distances from a query vector to the centroids of a `ProductQuantizer`
+///
+/// Used to compute asymmetric distances from the full-precision query to encoded vectors with
+/// one table lookup per subspace.
+pub struct DistanceTable<'a, M> {
+    quantizer: &'a ProductQuantizer<M>,
+    table: Vec<f32>,
+}
+
+impl<M: SubspaceMetric> DistanceTable<'_, M> {
+    /// The approximate distance from the query to the vector encoded as `code`
+    pub fn distance(&self, code: &PqCode) -> f32 {
+        let centroids = self.quantizer.centroids;
+        let sum = code
+            .codes
+            .iter()
+            .enumerate()
+            .map(|(subspace, &code)| self.table[subspace * centroids + code as usize])
+            .sum();
+        self.quantizer.metric.combine(sum)
+    }
+}
+
+impl<M: SubspaceMetric> Query<PqCode, ProductQuantizer<M>> for DistanceTable<'_, M> {
+    fn distance_to(&self, _: &ProductQuantizer<M>, point: &PqCode) -> f32 {
+        self.distance(point)
+    }
+}
+
+impl<M: SubspaceMetric> Hnsw<PqCode, ProductQuantizer<M>> {
+    /// Search the index for the points nearest to the full-precision vector `query`
+    ///
+    /// Distances are computed asymmetrically, from the query to the centroids of each point.
+    pub fn search_asymmetric<'a, 'b: 'a>(
+        &'b self,
+        query: &[f32],
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = Item<'b, PqCode>> + 'a {
+        self.search(&self.metric.table(query), search)
+    }
+}
+
+impl<V: Clone, M: SubspaceMetric> HnswMap<PqCode, V, ProductQuantizer<M>> {
+    /// Search the index for the points nearest to the full-precision vector `query`
+    ///
+    /// See `Hnsw::search_asymmetric()` for details.
+    pub fn search_asymmetric<'a>(
+        &'a self,
+        query: &[f32],
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = MapItem<'a, PqCode, V>> + 'a {
+        self.hnsw
+            .search_asymmetric(query, search)
+            .map(move |item| MapItem::from(item, self))
+    }
+}
+
+/// A vector encoded by a `ProductQuantizer`, using one byte per subspace
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct PqCode {
+    codes: Box<[u8]>,
+}
+
+impl PqCode {
+    /// The index of the centroid for each subspace
+    pub fn codes(&self) -> &[u8] {
+        &self.codes
+    }
+}
+
+/// A metric that can be computed by combining partial distances over subspaces
+///
+/// This is required for product quantization, which computes distances between vectors by
+/// adding up distances between their parts.
+pub trait SubspaceMetric: Metric {
+    /// Partial distance between the parts `a` and `b` of two vectors
+    fn partial(&self, a: &[f32], b: &[f32]) -> f32;
+
+    /// The distance for the sum of the partial distances over all subspaces
+    fn combine(&self, sum: f32) -> f32 {
+        sum
+    }
+}
+
+impl SubspaceMetric for Euclidean {
+    fn partial(&self, a: &[f32], b: &[f32]) -> f32 {
+        simd::l2_squared(a, b)
+    }
+
+    fn combine(&self, sum: f32) -> f32 {
+        sum.sqrt()
+    }
+}
+
+impl SubspaceMetric for SquaredEuclidean {
+    fn partial(&self, a: &[f32], b: &[f32]) -> f32 {
+        simd::l2_squared(a, b)
+    }
+}
+
+impl SubspaceMetric for InnerProduct {
+    fn partial(&self, a: &[f32], b: &[f32]) -> f32 {
+        -simd::dot(a, b)
+    }
+}
+
+impl SubspaceMetric for Manhattan {
+    fn partial(&self, a: &[f32], b: &[f32]) -> f32 {
+        self.distance(a, b)
+    }
+}
+
+/// The maximum number of centroids per subspace
+const CENTROIDS: usize = 256;
@@ -0,0 +1,233 @@
+//! Scalar quantization of dense `f32` vectors
+//!
+//! A `ScalarQuantizer` is trained from a set of vectors and encodes each value as a single byte,
+//! using a quarter of the memory needed for `f32` vectors. The quantizer is also the `Metric` for
+//! the encoded vectors, so it is passed to `Builder::build_with_metric()` and stored in the index.
+//!
+//! Searches can use either an encoded query or the full-precision query as a `[f32]` slice, which
+//! is compared to the decoded points. Distances computed from the codes are approximate. Use
+//! `ScalarQuantizer::rescore()` to recompute the distances for the candidates found by a search
+//! from the full-precision vectors, which can be kept elsewhere (for example, on disk) or as the
+//! values of an `HnswMap`:
+//!
+//! ```
+//! use instant_distance::metric::Euclidean;
+//! use instant_distance::quantize::{Granularity, ScalarQuantizer};
+//! use instant_distance::{Builder, Search};
+//!
+//! let vectors = vec![vec![0.0, 1.0], vec![1.0, 0.0], vec![0.5, 0.5]];
+//! let quantizer = ScalarQuantizer::<Euclidean>::train(&vectors, Granularity::PerDimension);
+//!
+//! let codes = vectors.iter().map(|v| quantizer.encode(v)).collect();
+//! let map = Builder::default().build_with_metric(codes, vectors, quantizer);
+//!
+//! let query = [0.9, 0.1];
+//! let mut search = Search::default();
+//! let found = map.search(&query[..], &mut search);
+//! let pids = found.map(|item| item.pid).collect::<Vec<_>>();
+//! let nearest = map.metric().rescore(&query, pids, |pid| &map.values[pid.into_inner() as usize]);
+//! assert_eq!(map.values[nearest[0].pid.into_inner() as usize], [1.0, 0.0]);
+//! ```
+
+use std::cell::RefCell;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::metric::{Euclidean, Metric};
+use crate::{Candidate, PointId, Query};
+
+/// Trained parameters for encoding vectors as bytes
+///
+/// Each value is mapped linearly from the range observed for its dimension (or vector) to the
+/// codes `0..=255`. Distances between encoded vectors are computed by decoding them and using
+/// the metric `M`.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug)]
+pub struct ScalarQuantizer<M = Euclidean> {
+    /// Offset and scale for each dimension, empty if ranges are tracked per vector
+    offsets: Vec<f32>,
+    scales: Vec<f32>,
+    metric: M,
+}
+
+impl<M: Default> ScalarQuantizer<M> {
+    /// Train a quantizer for vectors similar to `vectors`
+    ///
+    /// With `Granularity::PerDimension`, the range of each dimension is determined from
+    /// `vectors`; values outside of that range are clamped when they are encoded. Panics if
+    /// `vectors` is empty with this granularity.
+    pub fn train<V: AsRef<[f32]>>(vectors: &[V], granularity: Granularity) -> Self {
+        let (mut offsets, mut scales) = (Vec::new(), Vec::new());
+        if granularity == Granularity::PerDimension {
+            assert!(!vectors.is_empty(), "no training vectors");
+            let first = &vectors[0];
+            let mut max = first.as_ref().to_vec();
+            offsets = first.as_ref().to_vec();
+            for vector in vectors {
+                let vector = vector.as_ref();
+                assert_eq!(vector.len(), max.len(), "vectors must have the same length");
+                for ((min, max), &value) in offsets.iter_mut().zip(max.iter_mut()).zip(vector) {
+                    *min = min.min(value);
+                    *max = max.max(value);
+                }
+            }
+
+            scales = offsets
+                .iter()
+                .zip(&max)
+                .map(|(min, max)| (max - min) / CODES)
+                .collect();
+        }
+
+        Self {
+            offsets,
+            scales,
+            metric: M::default(),
+        }
+    }
+}
+
+impl<M> ScalarQuantizer<M> {
+    /// Encode `vector` as bytes
+    pub fn encode(&self, vector: &[f32]) -> QuantizedVector {
+        if self.offsets.is_empty() {
+            let min = vector.iter().copied().fold(f32::INFINITY, f32::min);
+            let max = vector.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+            let (offset, scale) = match vector.is_empty() {
+                true => (0.0, 0.0),
+                false => (min, (max - min) / CODES),
+            };
+
+            return QuantizedVector {
+                codes: vector.iter().map(|&x| quantize(x, offset, scale)).collect(),
+                offset,
+                scale,
+            };
+        }
+
+        assert_eq!(vector.len(), self.offsets.len(), "vector length mismatch");
+        let codes = vector
+            .iter()
+            .zip(self.offsets.iter().zip(&self.scales))
+            .map(|(&x, (&offset, &scale))| quantize(x, offset, scale))
+            .collect();
+
+        QuantizedVector {
+            codes,
+            offset: 0.0,
+            scale: 1.0,
+        }
+    }
+
+    /// Decode `vector` into `out`, replacing its contents
+    pub fn decode_into(&self, vector: &QuantizedVector, out: &mut Vec<f32>) {
+        out.clear();
+        let value = |code: u8| vector.offset + vector.scale * code as f32;
+        match self.offsets.is_empty() {
+            true => out.extend(vector.codes.iter().map(|&code| value(code))),
+            false => out.extend(
+                vector
+                    .codes
+                    .iter()
+                    .zip(self.offsets.iter().zip(&self.scales))
+                    .map(|(&code, (&offset, &scale))| offset + scale * value(code)),
+            ),
+        }
+    }
+
+    /// Decode `vector` into an approximation of the original vector
+    pub fn decode(&self, vector: &QuantizedVector) -> Vec<f32> {
+        let mut out = Vec::with_capacity(vector.codes.len());
+        self.decode_into(vector, &mut out);
+        out
+    }
+}
+
+impl<M: Metric> ScalarQuantizer<M> {
+    /// Recompute the distances from `query` to the points `pids` with full precision
+    ///
+    /// `full` returns the original vector for a given `PointId`. Returns the candidates sorted by
+    /// their exact distance.
+    pub fn rescore<V: AsRef<[f32]>>(
+        &self,
+        query: &[f32],
+        pids: impl IntoIterator<Item = PointId>,
+        full: impl Fn(PointId) -> V,
+    ) -> Vec<Candidate> {
+        let mut candidates = pids
+            .into_iter()
+            .map(|pid| Candidate::new(pid, self.metric.distance(query, full(pid).as_ref())))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable();
+        candidates
+    }
+}
+
+impl<M: Metric> Metric<QuantizedVector> for ScalarQuantizer<M> {
+    fn distance(&self, a: &QuantizedVector, b: &QuantizedVector) -> f32 {
+        DECODED.with(|decoded| {
+            let (a_buf, b_buf) = &mut *decoded.borrow_mut();
+            self.decode_into(a, a_buf);
+            self.decode_into(b, b_buf);
+            self.metric.distance(a_buf, b_buf)
+        })
+    }
+}
+
+impl<M: Metric> Query<QuantizedVector, ScalarQuantizer<M>> for [f32] {
+    fn distance_to(&self, quantizer: &ScalarQuantizer<M>, point: &QuantizedVector) -> f32 {
+        DECODED.with(|decoded| {
+            let (buf, _) = &mut *decoded.borrow_mut();
+            quantizer.decode_into(point, buf);
+            quantizer.metric.distance(self, buf)
+        })
+    }
+}
+
+thread_local! {
+    /// Buffers for decoding vectors to compute distances
+    static DECODED: RefCell<(Vec<f32>, Vec<f32>)> =
+        const { RefCell::new((Vec::new(), Vec::new())) };
+}
+
+/// Whether value ranges are tracked per dimension or per vector
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Granularity {
+    /// Use the range of each dimension over the training vectors
+    ///
+    /// This works well if the dimensions have different ranges, but values outside of the
+    /// training data's range lose precision.
+    PerDimension,
+    /// Use the range of each vector's values
+    ///
+    /// This does not need representative training data, but is less precise for vectors with
+    /// some dimensions that have much larger values than others.
+    PerVector,
+}
+
+/// A vector encoded by a `ScalarQuantizer`, using one byte per dimension
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuantizedVector {
+    codes: Box<[u8]>,
+    offset: f32,
+    scale: f32,
+}
+
+impl QuantizedVector {
+    /// The encoded values
+    pub fn codes(&self) -> &[u8] {
+        &self.codes
+    }
+}
+
+fn quantize(value: f32, offset: f32, scale: f32) -> u8 {
+    match scale > 0.0 {
+        true => ((value - offset) / scale).round().clamp(0.0, CODES) as u8,
+        false => 0,
+    }
+}
+
+/// The largest code value
+const CODES: f32 = u8::MAX as f32;
@@ -0,0 +1,138 @@
+use std::collections::VecDeque;
+
+use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
+
+use crate::metric::Metric;
+use crate::store::Permute;
+use crate::types::{Layer, LayerId, INVALID};
+use crate::{Hnsw, PointId};
+
+/// Strategy for renumbering the points after construction to improve memory locality
+///
+/// Construction stores the points in random order, so that the neighbors of a node are scattered
+/// across memory. Reordering renumbers the points so that nodes which are close together in the
+/// graph also end up close together in memory, which reduces cache misses during searches.
+///
+/// Points only move within the set of points sharing the same top layer, and the entry point
+/// always remains `PointId(0)`, so the layer structure of the graph is preserved.
+#[derive(Copy, Clone, Debug)]
+pub enum Reorder {
+    /// Number points in breadth-first order from the entry point
+    Bfs,
+    /// Number points in reverse Cuthill-McKee order
+    ///
+    /// This is a breadth-first traversal that visits neighbors in increasing order of their degree
+    /// and starts from low degree nodes, which tends to reduce the spread of neighbor identifiers.
+    ReverseCuthillMcKee,
+}
+
+impl<P: ?Sized, D: Metric<P>, S: Permute<Point = P>> Hnsw<P, D, S> {
+    /// Renumber the points in this index according to `strategy`
+    ///
+    /// `out` contains the `PointId` for each of the original points, which is updated to match.
+    pub(crate) fn reorder(&mut self, strategy: Reorder, out: &mut [PointId]) {
+        let len = self.points.len();
+        if len == 0 {
+            return;
+        }
+
+        let order = match strategy {
+            Reorder::Bfs => self.traverse(0..len as u32, |_| {}),
+            Reorder::ReverseCuthillMcKee => {
+                let degree = |pid: &PointId| self.zero.as_slice().nearest_iter(*pid).count();
+                let mut roots = (0..len as u32).collect::<Vec<_>>();
+                roots.sort_by_key(|&i| degree(&PointId(i)));
+                let mut order = self.traverse(roots, |neighbors| neighbors.sort_by_key(degree));
+                order.reverse();
+                order
+            }
+        };
+
+        // Assign new identifiers in traversal order, separately for the points of each top layer.
+        // Points on higher layers come first: layer `n` contains the first `layers[n - 1].len()`
+        // points.
+
+        let top = LayerId(self.layers.len());
+        let mut next = top
+            .descend()
+            .map(|layer| match layer == top {
+                true => 0,
+                false => self.layers[layer.0].len() as u32,
+            })
+            .collect::<Vec<_>>();
+        next.reverse();
+
+        let mut new = vec![INVALID; len];
+        new[0] = PointId(0);
+        next[top.0] += 1;
+        for pid in order.into_iter().filter(|pid| pid.0 != 0) {
+            let next = &mut next[self.top_layer(pid).0];
+            new[pid.0 as usize] = PointId(*next);
+            *next += 1;
+        }
+
+        // Permute the points and nodes in place by following the cycles in the permutation.
+        // Since points stay in the same layers, each cycle is either contained in a layer or
+        // outside of it.
+
+        let mut permutation = new.clone();
+        for i in 0..len {
+            while permutation[i].0 as usize != i {
+                let j = permutation[i].0 as usize;
+                self.points
+                    .swap_points(PointId(i as u32), PointId(j as u32));
+                self.zero.swap(i, j);
+                for layer in self.layers.iter_mut().take_while(|layer| i < layer.len()) {
+                    layer.swap(i, j);
+                }
+                permutation.swap(i, j);
+            }
+        }
+
+        self.zero.par_iter_mut().for_each(|node| node.remap(&new));
+        for layer in self.layers.iter_mut() {
+            layer.par_iter_mut().for_each(|node| node.remap(&new));
+        }
+
+        for pid in out.iter_mut() {
+            *pid = new[pid.0 as usize];
+        }
+    }
+
+    /// Traverse the zero layer breadth-first, returning the points in order of discovery
+    ///
+    /// Traversal starts from each of the `roots` that has not been discovered yet in turn.
+    /// `sort` may be used to change the order in which a node's neighbors are visited.
+    fn traverse(
+        &self,
+        roots: impl IntoIterator<Item = u32>,
+        mut sort: impl FnMut(&mut Vec<PointId>),
+    ) -> Vec<PointId> {
+        let mut seen = vec![false; self.points.len()];
+        let mut order = Vec::with_capacity(self.points.len());
+        let mut queue = VecDeque::new();
+        let mut neighbors = Vec::new();
+        for root in roots {
+            if seen[root as usize] {
+                continue;
+            }
+
+            seen[root as usize] = true;
+            queue.push_back(PointId(root));
+            while let Some(pid) = queue.pop_front() {
+                order.push(pid);
+                neighbors.clear();
+                neighbors.extend(self.zero.as_slice().nearest_iter(pid));
+                sort(&mut neighbors);
+                for &neighbor in neighbors.iter() {
+                    if !seen[neighbor.0 as usize] {
+                        seen[neighbor.0 as usize] = true;
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+
+        order
+    }
+}
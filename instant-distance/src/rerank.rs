@@ -0,0 +1,107 @@
+use std::borrow::Borrow;
+
+use ordered_float::OrderedFloat;
+
+use crate::metric::Metric;
+use crate::store::PointStore;
+use crate::{Hnsw, HnswMap, Item, MapItem, PointId, Query, Search};
+
+/// A source of points by `PointId`, used to rerank search results
+///
+/// This is implemented for slices and vectors of points in `PointId` order, and for any
+/// `Fn(PointId) -> R` where `R` borrows as a point, so that points can be loaded on demand from
+/// separate storage such as a memory-mapped file.
+pub trait PointSource<P: ?Sized> {
+    /// The type returned by `point()`
+    type Point<'a>: Borrow<P>
+    where
+        Self: 'a;
+
+    /// The point identified by `pid`
+    fn point(&self, pid: PointId) -> Self::Point<'_>;
+}
+
+impl<P: ?Sized, R: Borrow<P>, F: Fn(PointId) -> R> PointSource<P> for F {
+    type Point<'a>
+        = R
+    where
+        Self: 'a;
+
+    fn point(&self, pid: PointId) -> Self::Point<'_> {
+        self(pid)
+    }
+}
+
+impl<P> PointSource<P> for [P] {
+    type Point<'a>
+        = &'a P
+    where
+        Self: 'a;
+
+    fn point(&self, pid: PointId) -> Self::Point<'_> {
+        &self[pid]
+    }
+}
+
+impl<P> PointSource<P> for Vec<P> {
+    type Point<'a>
+        = &'a P
+    where
+        Self: 'a;
+
+    fn point(&self, pid: PointId) -> Self::Point<'_> {
+        &self.as_slice()[pid]
+    }
+}
+
+impl<P: ?Sized, D: Metric<P>, S: PointStore<Point = P>> Hnsw<P, D, S> {
+    /// Search the index for the points nearest to `approximate`, then rerank the results
+    ///
+    /// This navigates the graph using the points stored in the index, which may be a cheap
+    /// approximation (such as quantized or truncated vectors). The `ef_search` nearest candidates
+    /// found are then reranked by their exact distance to `query`, using the points from `source`
+    /// compared with `metric`. The distances of the returned items are the exact distances.
+    pub fn search_reranked<'a, 'b: 'a, Q: Query<P, D> + ?Sized, R: ?Sized>(
+        &'b self,
+        approximate: &Q,
+        query: &R,
+        source: &(impl PointSource<R> + ?Sized),
+        metric: &impl Metric<R>,
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = Item<'b, P>> + 'a {
+        self.search_with(approximate, &[], self.ef_search, search);
+        for candidate in search.nearest.iter_mut() {
+            let exact = metric.distance(query, source.point(candidate.pid).borrow());
+            candidate.distance = OrderedFloat::from(exact);
+        }
+
+        search.nearest.sort_unstable();
+        search
+            .iter()
+            .map(move |candidate| Item::new(candidate, &self.points))
+    }
+}
+
+impl<P, V, D, S> HnswMap<P, V, D, S>
+where
+    P: ?Sized,
+    V: Clone,
+    D: Metric<P>,
+    S: PointStore<Point = P>,
+{
+    /// Search the index for the points nearest to `approximate`, then rerank the results
+    ///
+    /// See `Hnsw::search_reranked()` for details.
+    pub fn search_reranked<'a, Q: Query<P, D> + ?Sized, R: ?Sized>(
+        &'a self,
+        approximate: &Q,
+        query: &R,
+        source: &(impl PointSource<R> + ?Sized),
+        metric: &impl Metric<R>,
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = MapItem<'a, P, V>> + 'a {
+        self.hnsw
+            .search_reranked(approximate, query, source, metric, search)
+            .map(move |item| MapItem::from(item, self))
+    }
+}
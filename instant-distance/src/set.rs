@@ -0,0 +1,227 @@
+//! Set types implementing `Point`, compared by Jaccard distance
+//!
+//! A `SortedSet` holds its elements in increasing order, so that the exact Jaccard distance
+//! `1 - |A ∩ B| / |A ∪ B|` is computed by merging two sets. For large sets, such as the shingles of
+//! long documents, a `MinHasher` computes fixed-size `MinHash` signatures instead: the fraction of
+//! positions in which two signatures differ is an estimate of the Jaccard distance of their sets.
+//!
+//! ```
+//! use instant_distance::set::{MinHasher, SortedSet};
+//! use instant_distance::Point;
+//!
+//! let a = SortedSet::new([1, 2, 3, 4]);
+//! let b = SortedSet::new([3, 4, 5, 6]);
+//! assert_eq!(a.distance(&b), 1.0 - 2.0 / 6.0);
+//!
+//! let hasher = MinHasher::new(128, 42);
+//! let estimate = hasher.sign(&a).distance(&hasher.sign(&b));
+//! assert!((estimate - a.distance(&b)).abs() < 0.2);
+//! ```
+
+use std::cmp::Ordering;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::Point;
+
+/// A set of elements, stored in increasing order
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        from = "Vec<T>",
+        into = "Vec<T>",
+        bound(
+            serialize = "T: Serialize + Clone",
+            deserialize = "T: Deserialize<'de> + Ord"
+        )
+    )
+)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SortedSet<T = u64> {
+    elements: Box<[T]>,
+}
+
+impl<T: Ord> SortedSet<T> {
+    /// Create a set from `elements` in any order, ignoring duplicates
+    pub fn new(elements: impl IntoIterator<Item = T>) -> Self {
+        let mut elements = elements.into_iter().collect::<Vec<_>>();
+        elements.sort_unstable();
+        elements.dedup();
+        Self {
+            elements: elements.into(),
+        }
+    }
+
+    /// Whether the set contains `element`
+    pub fn contains(&self, element: &T) -> bool {
+        self.elements.binary_search(element).is_ok()
+    }
+
+    /// The number of elements in both this set and `other`
+    pub fn intersection_len(&self, other: &Self) -> usize {
+        let (a, b) = (&self.elements, &other.elements);
+        let (mut i, mut j, mut len) = (0, 0, 0);
+        while i < a.len() && j < b.len() {
+            match a[i].cmp(&b[j]) {
+                Ordering::Less => i += 1,
+                Ordering::Greater => j += 1,
+                Ordering::Equal => {
+                    len += 1;
+                    i += 1;
+                    j += 1;
+                }
+            }
+        }
+        len
+    }
+}
+
+impl<T> SortedSet<T> {
+    /// The elements of the set, in increasing order
+    pub fn elements(&self) -> &[T] {
+        &self.elements
+    }
+
+    /// The number of elements in the set
+    pub fn len(&self) -> usize {
+        self.elements.len()
+    }
+
+    /// Whether the set has no elements
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+}
+
+impl<T: Ord> From<Vec<T>> for SortedSet<T> {
+    fn from(elements: Vec<T>) -> Self {
+        Self::new(elements)
+    }
+}
+
+impl<T> From<SortedSet<T>> for Vec<T> {
+    fn from(set: SortedSet<T>) -> Self {
+        set.elements.into_vec()
+    }
+}
+
+impl<T: Ord + Clone + Sync> Point for SortedSet<T> {
+    /// Jaccard distance, which is zero for two empty sets
+    fn distance(&self, other: &Self) -> f32 {
+        let intersection = self.intersection_len(other);
+        let union = self.len() + other.len() - intersection;
+        match union {
+            0 => 0.0,
+            _ => 1.0 - intersection as f32 / union as f32,
+        }
+    }
+}
+
+/// Computes `MinHash` signatures of sets of `u64` elements
+///
+/// Each position in a signature is the minimum over the set's elements of a different hash
+/// function. Signatures are only comparable if they were computed by the same `MinHasher`, so the
+/// hasher should be kept (or recreated from the same seed) to sign queries.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MinHasher {
+    /// Coefficients `(a, b)` of the hash functions `(a * x + b) mod MERSENNE_61`
+    coefficients: Box<[(u64, u64)]>,
+}
+
+impl MinHasher {
+    /// Create a hasher for signatures of length `len`, with hash functions chosen from `seed`
+    ///
+    /// The standard error of the estimated distance is about `1 / sqrt(len)`.
+    pub fn new(len: usize, seed: u64) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let coefficients = (0..len)
+            .map(|_| {
+                let a = rng.random_range(1..MERSENNE_61);
+                (a, rng.random_range(0..MERSENNE_61))
+            })
+            .collect();
+        Self { coefficients }
+    }
+
+    /// The signature of `set`
+    pub fn sign(&self, set: &SortedSet<u64>) -> MinHash {
+        self.sign_iter(set.elements().iter().copied())
+    }
+
+    /// The signature of the set of `elements`, which may contain duplicates
+    ///
+    /// The elements are typically hashes of the shingles of a document.
+    pub fn sign_iter(&self, elements: impl IntoIterator<Item = u64>) -> MinHash {
+        let mut values = vec![u64::MAX; self.coefficients.len()];
+        for element in elements {
+            let x = u128::from(element % MERSENNE_61);
+            for (value, &(a, b)) in values.iter_mut().zip(&self.coefficients) {
+                let hash = (u128::from(a) * x + u128::from(b)) % u128::from(MERSENNE_61);
+                *value = Ord::min(*value, hash as u64);
+            }
+        }
+
+        MinHash {
+            values: values.into(),
+        }
+    }
+
+    /// The length of the signatures
+    pub fn len(&self) -> usize {
+        self.coefficients.len()
+    }
+
+    /// Whether the signatures are empty
+    pub fn is_empty(&self) -> bool {
+        self.coefficients.is_empty()
+    }
+}
+
+/// A `MinHash` signature, computed by a `MinHasher`
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct MinHash {
+    values: Box<[u64]>,
+}
+
+impl MinHash {
+    /// The minimum hash value for each hash function
+    pub fn values(&self) -> &[u64] {
+        &self.values
+    }
+}
+
+impl Point for MinHash {
+    /// The fraction of positions in which the signatures differ
+    fn distance(&self, other: &Self) -> f32 {
+        debug_assert_eq!(self.values.len(), other.values.len());
+        let different = self
+            .values
+            .iter()
+            .zip(&other.values)
+            .filter(|(a, b)| a != b)
+            .count();
+        different as f32 / self.values.len().max(1) as f32
+    }
+
+    fn distance_bounded(&self, other: &Self, bound: f32) -> Option<f32> {
+        debug_assert_eq!(self.values.len(), other.values.len());
+        let len = self.values.len().max(1) as f32;
+        let mut different = 0;
+        for (a, b) in self.values.iter().zip(&other.values) {
+            different += usize::from(a != b);
+            if different as f32 / len > bound {
+                return None;
+            }
+        }
+        Some(different as f32 / len)
+    }
+}
+
+/// The Mersenne prime `2^61 - 1`, the modulus of the `MinHasher` hash functions
+const MERSENNE_61: u64 = (1 << 61) - 1;
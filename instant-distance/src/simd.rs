@@ -0,0 +1,450 @@
+//! SIMD kernels for dense `f32` vectors and bit vectors
+//!
+//! The functions in this module pick the best implementation for the current CPU at runtime:
+//! AVX-512, AVX2 (with FMA) or SSE2 on x86_64, NEON on aarch64, and a portable scalar
+//! implementation on other targets. They are used by the metrics in the `metric` module, and can
+//! be used directly to implement `Point` or `Metric` for other vector types.
+//!
+//! Results may differ from a naive scalar computation by small rounding errors, since the
+//! kernels sum in a different order.
+
+use std::sync::OnceLock;
+
+/// Squared Euclidean distance between `a` and `b`
+///
+/// Panics if the vectors have different lengths.
+pub fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len());
+    (kernels().l2_squared)(a, b)
+}
+
+/// Inner product of `a` and `b`
+///
+/// Panics if the vectors have different lengths.
+pub fn dot(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len());
+    (kernels().dot)(a, b)
+}
+
+/// Cosine distance between `a` and `b`, defined as one minus the cosine similarity
+///
+/// Returns 1 if either of the vectors is zero. Panics if the vectors have different lengths.
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    assert_eq!(a.len(), b.len());
+    cosine_distance((kernels().dot_norms)(a, b))
+}
+
+/// Hamming distance between the bit vectors `a` and `b`, the number of bits that differ
+///
+/// Panics if the vectors have different lengths.
+pub fn hamming(a: &[u64], b: &[u64]) -> u32 {
+    assert_eq!(a.len(), b.len());
+    (kernels().hamming)(a, b)
+}
+
+/// An instruction set for which this module provides kernels
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Level {
+    /// Portable implementation, available on all targets
+    Scalar,
+    /// SSE2, available on all x86_64 CPUs
+    Sse2,
+    /// AVX2, FMA and POPCNT on x86_64
+    Avx2,
+    /// AVX-512F and POPCNT on x86_64
+    Avx512,
+    /// NEON, available on all aarch64 CPUs
+    Neon,
+}
+
+impl Level {
+    /// The best level supported by the current CPU
+    pub fn detect() -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            for level in [Self::Avx512, Self::Avx2] {
+                if level.is_supported() {
+                    return level;
+                }
+            }
+            Self::Sse2
+        }
+        #[cfg(target_arch = "aarch64")]
+        {
+            Self::Neon
+        }
+        #[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+        {
+            Self::Scalar
+        }
+    }
+
+    /// Whether the kernels for this level can be used on the current CPU
+    pub fn is_supported(self) -> bool {
+        match self {
+            Self::Scalar => true,
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse2 => true,
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => {
+                is_x86_feature_detected!("avx2")
+                    && is_x86_feature_detected!("fma")
+                    && is_x86_feature_detected!("popcnt")
+            }
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512 => {
+                is_x86_feature_detected!("avx512f") && is_x86_feature_detected!("popcnt")
+            }
+            #[cfg(target_arch = "aarch64")]
+            Self::Neon => true,
+            _ => false,
+        }
+    }
+
+    /// Squared Euclidean distance between `a` and `b`, using the kernel for this level
+    ///
+    /// Panics if the level is not supported or the vectors have different lengths.
+    pub fn l2_squared(self, a: &[f32], b: &[f32]) -> f32 {
+        assert_eq!(a.len(), b.len());
+        (self.kernels().l2_squared)(a, b)
+    }
+
+    /// Inner product of `a` and `b`, using the kernel for this level
+    ///
+    /// Panics if the level is not supported or the vectors have different lengths.
+    pub fn dot(self, a: &[f32], b: &[f32]) -> f32 {
+        assert_eq!(a.len(), b.len());
+        (self.kernels().dot)(a, b)
+    }
+
+    /// Cosine distance between `a` and `b`, using the kernel for this level
+    ///
+    /// Panics if the level is not supported or the vectors have different lengths.
+    pub fn cosine(self, a: &[f32], b: &[f32]) -> f32 {
+        assert_eq!(a.len(), b.len());
+        cosine_distance((self.kernels().dot_norms)(a, b))
+    }
+
+    /// Hamming distance between the bit vectors `a` and `b`, using the kernel for this level
+    ///
+    /// Panics if the level is not supported or the vectors have different lengths.
+    pub fn hamming(self, a: &[u64], b: &[u64]) -> u32 {
+        assert_eq!(a.len(), b.len());
+        (self.kernels().hamming)(a, b)
+    }
+
+    fn kernels(self) -> &'static Kernels {
+        assert!(self.is_supported(), "{self:?} kernels not supported");
+        match self {
+            #[cfg(target_arch = "x86_64")]
+            Self::Sse2 => &x86::SSE2,
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx2 => &x86::AVX2,
+            #[cfg(target_arch = "x86_64")]
+            Self::Avx512 => &x86::AVX512,
+            #[cfg(target_arch = "aarch64")]
+            Self::Neon => &neon::NEON,
+            _ => &scalar::SCALAR,
+        }
+    }
+}
+
+/// The kernels for the detected level, selected on first use
+fn kernels() -> &'static Kernels {
+    static DETECTED: OnceLock<&'static Kernels> = OnceLock::new();
+    DETECTED.get_or_init(|| Level::detect().kernels())
+}
+
+fn cosine_distance([ab, aa, bb]: [f32; 3]) -> f32 {
+    let norms = (aa * bb).sqrt();
+    if norms == 0.0 {
+        return 1.0;
+    }
+
+    (1.0 - ab / norms).max(0.0)
+}
+
+/// Kernels for a single level
+///
+/// The kernels may assume that both slices have the same length, and that the level is supported
+/// by the current CPU.
+struct Kernels {
+    l2_squared: fn(&[f32], &[f32]) -> f32,
+    dot: fn(&[f32], &[f32]) -> f32,
+    /// Returns the inner product and the squared norms of both vectors
+    dot_norms: fn(&[f32], &[f32]) -> [f32; 3],
+    hamming: fn(&[u64], &[u64]) -> u32,
+}
+
+mod scalar {
+    use super::Kernels;
+
+    pub(super) const SCALAR: Kernels = Kernels {
+        l2_squared,
+        dot,
+        dot_norms,
+        hamming,
+    };
+
+    pub(super) fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+    }
+
+    pub(super) fn dot(a: &[f32], b: &[f32]) -> f32 {
+        a.iter().zip(b).map(|(a, b)| a * b).sum()
+    }
+
+    pub(super) fn dot_norms(a: &[f32], b: &[f32]) -> [f32; 3] {
+        a.iter().zip(b).fold([0.0; 3], |[ab, aa, bb], (a, b)| {
+            [ab + a * b, aa + a * a, bb + b * b]
+        })
+    }
+
+    pub(super) fn hamming(a: &[u64], b: &[u64]) -> u32 {
+        a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use std::arch::x86_64::*;
+
+    use super::{scalar, Kernels};
+
+    // Safety: SSE2 is always available on x86_64, and the other kernels are only selected if
+    // `Level::is_supported()` returns true
+
+    pub(super) const SSE2: Kernels = Kernels {
+        l2_squared: |a, b| unsafe { l2_squared_sse2(a, b) },
+        dot: |a, b| unsafe { dot_sse2(a, b) },
+        dot_norms: |a, b| unsafe { dot_norms_sse2(a, b) },
+        hamming: scalar::hamming,
+    };
+
+    pub(super) const AVX2: Kernels = Kernels {
+        l2_squared: |a, b| unsafe { l2_squared_avx2(a, b) },
+        dot: |a, b| unsafe { dot_avx2(a, b) },
+        dot_norms: |a, b| unsafe { dot_norms_avx2(a, b) },
+        hamming: |a, b| unsafe { hamming_popcnt(a, b) },
+    };
+
+    pub(super) const AVX512: Kernels = Kernels {
+        l2_squared: |a, b| unsafe { l2_squared_avx512(a, b) },
+        dot: |a, b| unsafe { dot_avx512(a, b) },
+        dot_norms: |a, b| unsafe { dot_norms_avx512(a, b) },
+        hamming: |a, b| unsafe { hamming_popcnt(a, b) },
+    };
+
+    #[target_feature(enable = "popcnt")]
+    unsafe fn hamming_popcnt(a: &[u64], b: &[u64]) -> u32 {
+        // With the `popcnt` feature enabled, `count_ones()` compiles to a single instruction
+        a.iter().zip(b).map(|(a, b)| (a ^ b).count_ones()).sum()
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn l2_squared_sse2(a: &[f32], b: &[f32]) -> f32 {
+        let mut acc = _mm_setzero_ps();
+        let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+        let tail = scalar::l2_squared(a_chunks.remainder(), b_chunks.remainder());
+        for (a, b) in a_chunks.zip(b_chunks) {
+            let diff = _mm_sub_ps(_mm_loadu_ps(a.as_ptr()), _mm_loadu_ps(b.as_ptr()));
+            acc = _mm_add_ps(acc, _mm_mul_ps(diff, diff));
+        }
+        sum_sse2(acc) + tail
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn dot_sse2(a: &[f32], b: &[f32]) -> f32 {
+        let mut acc = _mm_setzero_ps();
+        let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+        let tail = scalar::dot(a_chunks.remainder(), b_chunks.remainder());
+        for (a, b) in a_chunks.zip(b_chunks) {
+            let product = _mm_mul_ps(_mm_loadu_ps(a.as_ptr()), _mm_loadu_ps(b.as_ptr()));
+            acc = _mm_add_ps(acc, product);
+        }
+        sum_sse2(acc) + tail
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn dot_norms_sse2(a: &[f32], b: &[f32]) -> [f32; 3] {
+        let (mut ab, mut aa, mut bb) = (_mm_setzero_ps(), _mm_setzero_ps(), _mm_setzero_ps());
+        let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+        let tail = scalar::dot_norms(a_chunks.remainder(), b_chunks.remainder());
+        for (a, b) in a_chunks.zip(b_chunks) {
+            let (a, b) = (_mm_loadu_ps(a.as_ptr()), _mm_loadu_ps(b.as_ptr()));
+            ab = _mm_add_ps(ab, _mm_mul_ps(a, b));
+            aa = _mm_add_ps(aa, _mm_mul_ps(a, a));
+            bb = _mm_add_ps(bb, _mm_mul_ps(b, b));
+        }
+        [
+            sum_sse2(ab) + tail[0],
+            sum_sse2(aa) + tail[1],
+            sum_sse2(bb) + tail[2],
+        ]
+    }
+
+    #[target_feature(enable = "sse2")]
+    unsafe fn sum_sse2(acc: __m128) -> f32 {
+        let acc = _mm_add_ps(acc, _mm_movehl_ps(acc, acc));
+        let acc = _mm_add_ss(acc, _mm_shuffle_ps(acc, acc, 0x1));
+        _mm_cvtss_f32(acc)
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn l2_squared_avx2(a: &[f32], b: &[f32]) -> f32 {
+        let mut acc = _mm256_setzero_ps();
+        let (a_chunks, b_chunks) = (a.chunks_exact(8), b.chunks_exact(8));
+        let tail = scalar::l2_squared(a_chunks.remainder(), b_chunks.remainder());
+        for (a, b) in a_chunks.zip(b_chunks) {
+            let diff = _mm256_sub_ps(_mm256_loadu_ps(a.as_ptr()), _mm256_loadu_ps(b.as_ptr()));
+            acc = _mm256_fmadd_ps(diff, diff, acc);
+        }
+        sum_avx2(acc) + tail
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn dot_avx2(a: &[f32], b: &[f32]) -> f32 {
+        let mut acc = _mm256_setzero_ps();
+        let (a_chunks, b_chunks) = (a.chunks_exact(8), b.chunks_exact(8));
+        let tail = scalar::dot(a_chunks.remainder(), b_chunks.remainder());
+        for (a, b) in a_chunks.zip(b_chunks) {
+            acc = _mm256_fmadd_ps(
+                _mm256_loadu_ps(a.as_ptr()),
+                _mm256_loadu_ps(b.as_ptr()),
+                acc,
+            );
+        }
+        sum_avx2(acc) + tail
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn dot_norms_avx2(a: &[f32], b: &[f32]) -> [f32; 3] {
+        let mut ab = _mm256_setzero_ps();
+        let (mut aa, mut bb) = (_mm256_setzero_ps(), _mm256_setzero_ps());
+        let (a_chunks, b_chunks) = (a.chunks_exact(8), b.chunks_exact(8));
+        let tail = scalar::dot_norms(a_chunks.remainder(), b_chunks.remainder());
+        for (a, b) in a_chunks.zip(b_chunks) {
+            let (a, b) = (_mm256_loadu_ps(a.as_ptr()), _mm256_loadu_ps(b.as_ptr()));
+            ab = _mm256_fmadd_ps(a, b, ab);
+            aa = _mm256_fmadd_ps(a, a, aa);
+            bb = _mm256_fmadd_ps(b, b, bb);
+        }
+        [
+            sum_avx2(ab) + tail[0],
+            sum_avx2(aa) + tail[1],
+            sum_avx2(bb) + tail[2],
+        ]
+    }
+
+    #[target_feature(enable = "avx2,fma")]
+    unsafe fn sum_avx2(acc: __m256) -> f32 {
+        let upper = _mm256_extractf128_ps(acc, 1);
+        sum_sse2(_mm_add_ps(upper, _mm256_castps256_ps128(acc)))
+    }
+
+    /// Load up to 16 values, filling the remaining lanes with zeros
+    #[target_feature(enable = "avx512f")]
+    unsafe fn load_avx512(values: &[f32]) -> __m512 {
+        debug_assert!(values.len() <= 16);
+        let mask = (1u32 << values.len()).wrapping_sub(1) as __mmask16;
+        _mm512_maskz_loadu_ps(mask, values.as_ptr())
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn l2_squared_avx512(a: &[f32], b: &[f32]) -> f32 {
+        let mut acc = _mm512_setzero_ps();
+        for (a, b) in a.chunks(16).zip(b.chunks(16)) {
+            let diff = _mm512_sub_ps(load_avx512(a), load_avx512(b));
+            acc = _mm512_fmadd_ps(diff, diff, acc);
+        }
+        _mm512_reduce_add_ps(acc)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn dot_avx512(a: &[f32], b: &[f32]) -> f32 {
+        let mut acc = _mm512_setzero_ps();
+        for (a, b) in a.chunks(16).zip(b.chunks(16)) {
+            acc = _mm512_fmadd_ps(load_avx512(a), load_avx512(b), acc);
+        }
+        _mm512_reduce_add_ps(acc)
+    }
+
+    #[target_feature(enable = "avx512f")]
+    unsafe fn dot_norms_avx512(a: &[f32], b: &[f32]) -> [f32; 3] {
+        let mut ab = _mm512_setzero_ps();
+        let (mut aa, mut bb) = (_mm512_setzero_ps(), _mm512_setzero_ps());
+        for (a, b) in a.chunks(16).zip(b.chunks(16)) {
+            let (a, b) = (load_avx512(a), load_avx512(b));
+            ab = _mm512_fmadd_ps(a, b, ab);
+            aa = _mm512_fmadd_ps(a, a, aa);
+            bb = _mm512_fmadd_ps(b, b, bb);
+        }
+        [
+            _mm512_reduce_add_ps(ab),
+            _mm512_reduce_add_ps(aa),
+            _mm512_reduce_add_ps(bb),
+        ]
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use std::arch::aarch64::*;
+
+    use super::{scalar, Kernels};
+
+    pub(super) const NEON: Kernels = Kernels {
+        l2_squared,
+        dot,
+        dot_norms,
+        // `count_ones()` already compiles to NEON instructions on aarch64
+        hamming: scalar::hamming,
+    };
+
+    fn l2_squared(a: &[f32], b: &[f32]) -> f32 {
+        let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+        let tail = scalar::l2_squared(a_chunks.remainder(), b_chunks.remainder());
+        // Safety: NEON is always available on aarch64, and each chunk contains 4 values
+        unsafe {
+            let mut acc = vdupq_n_f32(0.0);
+            for (a, b) in a_chunks.zip(b_chunks) {
+                let diff = vsubq_f32(vld1q_f32(a.as_ptr()), vld1q_f32(b.as_ptr()));
+                acc = vfmaq_f32(acc, diff, diff);
+            }
+            vaddvq_f32(acc) + tail
+        }
+    }
+
+    fn dot(a: &[f32], b: &[f32]) -> f32 {
+        let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+        let tail = scalar::dot(a_chunks.remainder(), b_chunks.remainder());
+        // Safety: NEON is always available on aarch64, and each chunk contains 4 values
+        unsafe {
+            let mut acc = vdupq_n_f32(0.0);
+            for (a, b) in a_chunks.zip(b_chunks) {
+                acc = vfmaq_f32(acc, vld1q_f32(a.as_ptr()), vld1q_f32(b.as_ptr()));
+            }
+            vaddvq_f32(acc) + tail
+        }
+    }
+
+    fn dot_norms(a: &[f32], b: &[f32]) -> [f32; 3] {
+        let (a_chunks, b_chunks) = (a.chunks_exact(4), b.chunks_exact(4));
+        let tail = scalar::dot_norms(a_chunks.remainder(), b_chunks.remainder());
+        // Safety: NEON is always available on aarch64, and each chunk contains 4 values
+        unsafe {
+            let (mut ab, mut aa, mut bb) = (vdupq_n_f32(0.0), vdupq_n_f32(0.0), vdupq_n_f32(0.0));
+            for (a, b) in a_chunks.zip(b_chunks) {
+                let (a, b) = (vld1q_f32(a.as_ptr()), vld1q_f32(b.as_ptr()));
+                ab = vfmaq_f32(ab, a, b);
+                aa = vfmaq_f32(aa, a, a);
+                bb = vfmaq_f32(bb, b, b);
+            }
+            [
+                vaddvq_f32(ab) + tail[0],
+                vaddvq_f32(aa) + tail[1],
+                vaddvq_f32(bb) + tail[2],
+            ]
+        }
+    }
+}
@@ -0,0 +1,238 @@
+//! Sparse vector type implementing `Point`
+//!
+//! A `SparseVec` stores only the non-zero values of a vector along with their indices, sorted by
+//! index. This suits learned sparse representations such as SPLADE, which have a large
+//! vocabulary-sized dimensionality with few non-zero values per vector.
+//!
+//! Distances are computed by merging the sorted indices of both vectors. The metric is selected
+//! with a type parameter, like for the dense vector types: `SparseVec<InnerProduct>` or
+//! `SparseVec<Cosine>`. Both metrics also implement `Metric` for sparse vectors, so they can be
+//! passed to `Builder::build_with_metric()` instead.
+
+use std::cmp::Ordering;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::metric::{Cosine, InnerProduct, Metric};
+use crate::Point;
+
+/// A sparse vector of `f32` values with `u32` indices
+///
+/// When serialized, the vector is recorded as its `indices` and `values` arrays.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "Parts",
+        into = "Parts",
+        bound(deserialize = "M: Default", serialize = "M: Clone")
+    )
+)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct SparseVec<M = InnerProduct> {
+    indices: Box<[u32]>,
+    values: Box<[f32]>,
+    /// The Euclidean norm of the vector
+    norm: f32,
+    metric: M,
+}
+
+impl<M: Default> SparseVec<M> {
+    /// Create a new vector from the given `indices` and their `values`
+    ///
+    /// Panics if `indices` and `values` have different lengths, or if the indices are not
+    /// strictly increasing. Use `from_pairs()` for unsorted indices.
+    pub fn new(indices: impl Into<Box<[u32]>>, values: impl Into<Box<[f32]>>) -> Self {
+        match Self::try_new(indices.into(), values.into()) {
+            Ok(vector) => vector,
+            Err(error) => panic!("{error}"),
+        }
+    }
+
+    /// Create a vector from `(index, value)` pairs in any order
+    ///
+    /// Values for the same index are added up.
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (u32, f32)>) -> Self {
+        let mut pairs = pairs.into_iter().collect::<Vec<_>>();
+        pairs.sort_unstable_by_key(|&(index, _)| index);
+        pairs.dedup_by(|(index, value), (prev_index, prev_value)| {
+            let duplicate = index == prev_index;
+            if duplicate {
+                *prev_value += *value;
+            }
+            duplicate
+        });
+
+        let (indices, values) = pairs.into_iter().unzip::<_, _, Vec<_>, Vec<_>>();
+        Self::new(indices, values)
+    }
+
+    /// Create a vector from the non-zero values of the dense `vector`
+    pub fn from_dense(vector: &[f32]) -> Self {
+        let pairs = vector.iter().enumerate().filter(|(_, &value)| value != 0.0);
+        let (indices, values) = pairs
+            .map(|(index, &value)| (index as u32, value))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        Self::new(indices, values)
+    }
+
+    fn try_new(indices: Box<[u32]>, values: Box<[f32]>) -> Result<Self, &'static str> {
+        if indices.len() != values.len() {
+            return Err("indices and values must have the same length");
+        } else if !indices.windows(2).all(|w| w[0] < w[1]) {
+            return Err("indices must be strictly increasing");
+        }
+
+        Ok(Self {
+            norm: values.iter().map(|x| x * x).sum::<f32>().sqrt(),
+            indices,
+            values,
+            metric: M::default(),
+        })
+    }
+}
+
+impl<M> SparseVec<M> {
+    /// The indices of the non-zero values, in increasing order
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// The non-zero values, in the same order as `indices()`
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Iterate over the `(index, value)` pairs
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (u32, f32)> + '_ {
+        self.indices
+            .iter()
+            .copied()
+            .zip(self.values.iter().copied())
+    }
+
+    /// The number of stored values
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Whether the vector has no stored values
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// The Euclidean norm of the vector
+    pub fn norm(&self) -> f32 {
+        self.norm
+    }
+
+    /// Return the vector's indices and values
+    pub fn into_parts(self) -> (Box<[u32]>, Box<[f32]>) {
+        (self.indices, self.values)
+    }
+
+    /// The inner product of this vector and `other`
+    pub fn dot(&self, other: &Self) -> f32 {
+        let (short, long) = match self.len() <= other.len() {
+            true => (self, other),
+            false => (other, self),
+        };
+
+        match long.len() > short.len() * GALLOP_RATIO {
+            true => gallop(short, long),
+            false => merge(short, long),
+        }
+    }
+}
+
+impl<M: Metric<Self> + Clone> Point for SparseVec<M> {
+    fn distance(&self, other: &Self) -> f32 {
+        self.metric.distance(self, other)
+    }
+}
+
+impl<M> Metric<SparseVec<M>> for InnerProduct {
+    fn distance(&self, a: &SparseVec<M>, b: &SparseVec<M>) -> f32 {
+        -a.dot(b)
+    }
+}
+
+impl<M> Metric<SparseVec<M>> for Cosine {
+    fn distance(&self, a: &SparseVec<M>, b: &SparseVec<M>) -> f32 {
+        let norms = a.norm * b.norm;
+        if norms == 0.0 {
+            return 1.0;
+        }
+
+        (1.0 - a.dot(b) / norms).max(0.0)
+    }
+}
+
+/// Inner product of two sparse vectors, stepping through both sets of indices together
+fn merge<M>(a: &SparseVec<M>, b: &SparseVec<M>) -> f32 {
+    let (mut i, mut j, mut sum) = (0, 0, 0.0);
+    while i < a.indices.len() && j < b.indices.len() {
+        match a.indices[i].cmp(&b.indices[j]) {
+            Ordering::Less => i += 1,
+            Ordering::Greater => j += 1,
+            Ordering::Equal => {
+                sum += a.values[i] * b.values[j];
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    sum
+}
+
+/// Inner product of two sparse vectors, searching `long` for each of the indices in `short`
+///
+/// This is faster than `merge()` if `long` has many more values than `short`.
+fn gallop<M>(short: &SparseVec<M>, long: &SparseVec<M>) -> f32 {
+    let (mut start, mut sum) = (0, 0.0);
+    for (index, value) in short.iter() {
+        match long.indices[start..].binary_search(&index) {
+            Ok(i) => {
+                sum += value * long.values[start + i];
+                start += i + 1;
+            }
+            Err(i) => start += i,
+        }
+
+        if start == long.indices.len() {
+            break;
+        }
+    }
+    sum
+}
+
+/// Serialized representation of a `SparseVec`
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+struct Parts {
+    indices: Box<[u32]>,
+    values: Box<[f32]>,
+}
+
+#[cfg(feature = "serde")]
+impl<M: Default> TryFrom<Parts> for SparseVec<M> {
+    type Error = &'static str;
+
+    fn try_from(parts: Parts) -> Result<Self, Self::Error> {
+        Self::try_new(parts.indices, parts.values)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<M> From<SparseVec<M>> for Parts {
+    fn from(vector: SparseVec<M>) -> Self {
+        Self {
+            indices: vector.indices,
+            values: vector.values,
+        }
+    }
+}
+
+/// Length ratio above which `gallop()` is used instead of `merge()`
+const GALLOP_RATIO: usize = 8;
@@ -0,0 +1,498 @@
+//! Storage for the points in an index
+//!
+//! `Hnsw` and `HnswMap` keep their points in a `PointStore`, which is a `Vec<P>` by default. For
+//! dense vectors with a dimensionality chosen at runtime, an `Arena` stores all vectors in one
+//! allocation instead, avoiding a separate allocation per point. Its points are `[f32]` slices:
+//!
+//! ```
+//! use instant_distance::metric::Euclidean;
+//! use instant_distance::store::Arena;
+//! use instant_distance::{Builder, Search};
+//!
+//! let mut arena = Arena::new(3);
+//! arena.push(&[0.0, 0.0, 1.0]);
+//! arena.push(&[0.0, 1.0, 0.0]);
+//! arena.push(&[1.0, 0.0, 0.0]);
+//!
+//! let (hnsw, pids) = Builder::default().build_hnsw_with_store(arena, Euclidean);
+//! let mut search = Search::default();
+//! let nearest = hnsw.search(&[0.9, 0.1, 0.0][..], &mut search).next().unwrap();
+//! assert_eq!(nearest.pid, pids[2]);
+//! assert_eq!(nearest.point, [1.0, 0.0, 0.0]);
+//! ```
+//!
+//! Any type implementing `PointStore` can hold the points, so they can also live in memory owned
+//! elsewhere, in a memory-mapped file (see `Flat`), in a quantized representation or be computed
+//! on demand (see `Lazy`). Building an index reorders the points in its store; wrap a store in
+//! `Mapped` to record the order as a list of identifiers instead. This allows several indexes,
+//! built with different parameters, to share one store through `&S` or `Arc<S>`.
+
+use std::fmt;
+use std::slice;
+use std::sync::{Arc, OnceLock};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::PointId;
+
+/// Storage for the points in an index, identified by `PointId`
+pub trait PointStore: Sync {
+    /// The type of the stored points
+    type Point: ?Sized;
+
+    /// The point identified by `pid`
+    fn point(&self, pid: PointId) -> &Self::Point;
+
+    /// The number of stored points
+    fn len(&self) -> usize;
+
+    /// Whether the store contains no points
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// A `PointStore` in which points can be moved around
+///
+/// Building an index reorders the points, so that the points on the higher layers of the graph
+/// come first.
+pub trait Permute: PointStore {
+    /// Swap the points identified by `a` and `b`
+    fn swap_points(&mut self, a: PointId, b: PointId);
+}
+
+impl<P: Sync> PointStore for Vec<P> {
+    type Point = P;
+
+    fn point(&self, pid: PointId) -> &P {
+        &self.as_slice()[pid]
+    }
+
+    fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+}
+
+impl<P: Sync> Permute for Vec<P> {
+    fn swap_points(&mut self, a: PointId, b: PointId) {
+        self.as_mut_slice()
+            .swap(a.into_inner() as usize, b.into_inner() as usize);
+    }
+}
+
+impl<S: PointStore + ?Sized> PointStore for &S {
+    type Point = S::Point;
+
+    fn point(&self, pid: PointId) -> &S::Point {
+        (**self).point(pid)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
+impl<S: PointStore + Send + ?Sized> PointStore for Arc<S> {
+    type Point = S::Point;
+
+    fn point(&self, pid: PointId) -> &S::Point {
+        (**self).point(pid)
+    }
+
+    fn len(&self) -> usize {
+        (**self).len()
+    }
+}
+
+/// A `PointStore` viewed in a different order, without moving its points
+///
+/// `Mapped` implements `Permute` for any store by swapping identifiers rather than points, so that
+/// indexes can be built over stores that are shared or read-only. Use `id()` to find the identifier
+/// of a point in the underlying store.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "MappedParts<S>",
+        bound(deserialize = "S: PointStore + Deserialize<'de>")
+    )
+)]
+#[derive(Clone, Debug)]
+pub struct Mapped<S> {
+    store: S,
+    /// Identifiers in `store`, in the order of this view
+    ids: Vec<PointId>,
+}
+
+impl<S: PointStore> Mapped<S> {
+    /// View all the points in `store`, in their original order
+    pub fn new(store: S) -> Self {
+        let ids = (0..store.len()).map(|i| PointId(i as u32)).collect();
+        Self { store, ids }
+    }
+
+    /// The identifier in the underlying store of the point identified by `pid`
+    pub fn id(&self, pid: PointId) -> PointId {
+        self.ids[pid.into_inner() as usize]
+    }
+
+    /// The underlying store
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Return the underlying store
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+}
+
+impl<S: PointStore> PointStore for Mapped<S> {
+    type Point = S::Point;
+
+    fn point(&self, pid: PointId) -> &S::Point {
+        self.store.point(self.id(pid))
+    }
+
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl<S: PointStore> Permute for Mapped<S> {
+    fn swap_points(&mut self, a: PointId, b: PointId) {
+        self.ids
+            .swap(a.into_inner() as usize, b.into_inner() as usize);
+    }
+}
+
+/// Serialized representation of a `Mapped` store
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct MappedParts<S> {
+    store: S,
+    ids: Vec<PointId>,
+}
+
+#[cfg(feature = "serde")]
+impl<S: PointStore> TryFrom<MappedParts<S>> for Mapped<S> {
+    type Error = &'static str;
+
+    fn try_from(parts: MappedParts<S>) -> Result<Self, Self::Error> {
+        let len = parts.store.len();
+        match parts.ids.iter().all(|id| (id.into_inner() as usize) < len) {
+            true => Ok(Self {
+                store: parts.store,
+                ids: parts.ids,
+            }),
+            false => Err("identifiers must refer to points in the store"),
+        }
+    }
+}
+
+/// Points computed on demand by a function of their `PointId`
+///
+/// Each point is computed the first time it is needed and kept from then on. A `Lazy` store can't
+/// be reordered, so wrap it in `Mapped` to build an index over it.
+pub struct Lazy<P, F> {
+    points: Vec<OnceLock<P>>,
+    compute: F,
+}
+
+impl<P, F: Fn(PointId) -> P> Lazy<P, F> {
+    /// Create a store of `len` points, computed by `compute`
+    pub fn new(len: usize, compute: F) -> Self {
+        Self {
+            points: (0..len).map(|_| OnceLock::new()).collect(),
+            compute,
+        }
+    }
+}
+
+impl<P: Send + Sync, F: Fn(PointId) -> P + Sync> PointStore for Lazy<P, F> {
+    type Point = P;
+
+    fn point(&self, pid: PointId) -> &P {
+        self.points[pid.into_inner() as usize].get_or_init(|| (self.compute)(pid))
+    }
+
+    fn len(&self) -> usize {
+        self.points.len()
+    }
+}
+
+impl<P: fmt::Debug, F> fmt::Debug for Lazy<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.points).finish()
+    }
+}
+
+/// Dense `f32` vectors of the same dimensionality, read from consecutive values
+///
+/// Unlike `Arena`, the values can be held by any type implementing `AsRef<[f32]>`, such as a slice
+/// of a memory-mapped file. A `Flat` store can't be reordered, so wrap it in `Mapped` to build an
+/// index over it.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "FlatParts<V>",
+        bound(deserialize = "V: AsRef<[f32]> + Deserialize<'de>")
+    )
+)]
+#[derive(Clone, Debug)]
+pub struct Flat<V> {
+    dimensions: usize,
+    values: V,
+}
+
+impl<V: AsRef<[f32]>> Flat<V> {
+    /// View `values` as consecutive vectors of `dimensions` values each
+    ///
+    /// Panics if the length of `values` is not a multiple of `dimensions`.
+    pub fn new(dimensions: usize, values: V) -> Self {
+        assert!(
+            dimensions > 0 && values.as_ref().len().is_multiple_of(dimensions),
+            "values must contain a whole number of vectors"
+        );
+        Self { dimensions, values }
+    }
+
+    /// The number of values in each vector
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// Return the underlying values
+    pub fn into_inner(self) -> V {
+        self.values
+    }
+}
+
+impl<V: AsRef<[f32]> + Sync> PointStore for Flat<V> {
+    type Point = [f32];
+
+    fn point(&self, pid: PointId) -> &[f32] {
+        let start = pid.into_inner() as usize * self.dimensions;
+        &self.values.as_ref()[start..start + self.dimensions]
+    }
+
+    fn len(&self) -> usize {
+        self.values.as_ref().len() / self.dimensions
+    }
+}
+
+/// Serialized representation of a `Flat` store
+#[cfg(feature = "serde")]
+#[derive(Deserialize)]
+struct FlatParts<V> {
+    dimensions: usize,
+    values: V,
+}
+
+#[cfg(feature = "serde")]
+impl<V: AsRef<[f32]>> TryFrom<FlatParts<V>> for Flat<V> {
+    type Error = &'static str;
+
+    fn try_from(parts: FlatParts<V>) -> Result<Self, Self::Error> {
+        let len = parts.values.as_ref().len();
+        match parts.dimensions > 0 && len.is_multiple_of(parts.dimensions) {
+            true => Ok(Self {
+                dimensions: parts.dimensions,
+                values: parts.values,
+            }),
+            false => Err("values must contain a whole number of vectors"),
+        }
+    }
+}
+
+/// Dense `f32` vectors of the same dimensionality, stored contiguously
+///
+/// Each vector is padded to a multiple of 16 values, so that every vector starts on a 64-byte
+/// boundary. When serialized, the vectors are recorded without padding.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "ArenaParts", into = "ArenaParts"))]
+#[derive(Clone)]
+pub struct Arena {
+    dimensions: usize,
+    /// The number of values used by each vector, including padding
+    stride: usize,
+    len: usize,
+    blocks: Vec<Block>,
+}
+
+impl Arena {
+    /// Create an empty arena for vectors with the given number of `dimensions`
+    pub fn new(dimensions: usize) -> Self {
+        Self::with_capacity(dimensions, 0)
+    }
+
+    /// Create an empty arena with space for `capacity` vectors
+    pub fn with_capacity(dimensions: usize, capacity: usize) -> Self {
+        let stride = dimensions.next_multiple_of(BLOCK);
+        Self {
+            dimensions,
+            stride,
+            len: 0,
+            blocks: Vec::with_capacity(capacity * stride / BLOCK),
+        }
+    }
+
+    /// Create an arena from `values`, containing consecutive vectors of `dimensions` values each
+    ///
+    /// Panics if the length of `values` is not a multiple of `dimensions`.
+    pub fn from_flat(dimensions: usize, values: &[f32]) -> Self {
+        assert!(
+            dimensions > 0 && values.len().is_multiple_of(dimensions),
+            "values must contain a whole number of vectors"
+        );
+
+        let mut arena = Self::with_capacity(dimensions, values.len() / dimensions);
+        values
+            .chunks_exact(dimensions)
+            .for_each(|vector| arena.push(vector));
+        arena
+    }
+
+    /// Append `vector` to the arena
+    ///
+    /// Panics if the length of `vector` differs from the arena's dimensionality.
+    pub fn push(&mut self, vector: &[f32]) {
+        assert_eq!(vector.len(), self.dimensions, "vector length mismatch");
+        let start = self.len * self.stride;
+        self.blocks
+            .resize((start + self.stride) / BLOCK, Block([0.0; BLOCK]));
+        let end = start + self.dimensions;
+        self.values_mut()[start..end].copy_from_slice(vector);
+        self.len += 1;
+    }
+
+    /// The vector at `index`, if there is one
+    pub fn get(&self, index: usize) -> Option<&[f32]> {
+        match index < self.len {
+            true => Some(self.vector(index)),
+            false => None,
+        }
+    }
+
+    /// Iterate over the vectors in the arena
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = &[f32]> + '_ {
+        (0..self.len).map(|i| self.vector(i))
+    }
+
+    /// The number of values in each vector
+    pub fn dimensions(&self) -> usize {
+        self.dimensions
+    }
+
+    /// The number of vectors in the arena
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the arena contains no vectors
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn vector(&self, index: usize) -> &[f32] {
+        let start = index * self.stride;
+        &self.values()[start..start + self.dimensions]
+    }
+
+    fn values(&self) -> &[f32] {
+        // Safety: `Block` is an array of `f32` values without any padding
+        unsafe { slice::from_raw_parts(self.blocks.as_ptr().cast(), self.blocks.len() * BLOCK) }
+    }
+
+    fn values_mut(&mut self) -> &mut [f32] {
+        // Safety: `Block` is an array of `f32` values without any padding
+        unsafe {
+            slice::from_raw_parts_mut(self.blocks.as_mut_ptr().cast(), self.blocks.len() * BLOCK)
+        }
+    }
+}
+
+impl PointStore for Arena {
+    type Point = [f32];
+
+    fn point(&self, pid: PointId) -> &[f32] {
+        self.vector(pid.into_inner() as usize)
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+impl Permute for Arena {
+    fn swap_points(&mut self, a: PointId, b: PointId) {
+        let (a, b) = (a.into_inner() as usize, b.into_inner() as usize);
+        let (a, b) = (Ord::min(a, b), Ord::max(a, b));
+        if a == b {
+            return;
+        }
+
+        let stride = self.stride;
+        let (head, tail) = self.values_mut().split_at_mut(b * stride);
+        head[a * stride..(a + 1) * stride].swap_with_slice(&mut tail[..stride]);
+    }
+}
+
+impl fmt::Debug for Arena {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// A cache line worth of values
+#[derive(Clone, Copy)]
+#[repr(C, align(64))]
+struct Block([f32; BLOCK]);
+
+/// Serialized representation of an `Arena`
+#[cfg(feature = "serde")]
+#[derive(Deserialize, Serialize)]
+struct ArenaParts {
+    dimensions: usize,
+    /// The number of vectors, which can't be derived from the values if `dimensions` is zero
+    len: usize,
+    values: Vec<f32>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<ArenaParts> for Arena {
+    type Error = &'static str;
+
+    fn try_from(parts: ArenaParts) -> Result<Self, Self::Error> {
+        let ArenaParts {
+            dimensions,
+            len,
+            values,
+        } = parts;
+        if len.checked_mul(dimensions) != Some(values.len()) {
+            return Err("values must contain `len` vectors of `dimensions` values");
+        }
+
+        let mut arena = Self::with_capacity(dimensions, len);
+        for i in 0..len {
+            arena.push(&values[i * dimensions..(i + 1) * dimensions]);
+        }
+        Ok(arena)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<Arena> for ArenaParts {
+    fn from(arena: Arena) -> Self {
+        Self {
+            dimensions: arena.dimensions,
+            len: arena.len,
+            values: arena.iter().flatten().copied().collect(),
+        }
+    }
+}
+
+/// The number of values in a `Block`
+const BLOCK: usize = 16;
@@ -0,0 +1,267 @@
+//! String type implementing `Point`, compared by edit distance
+//!
+//! A `Text` holds a string as a sequence of `char`s, so that edit distances count Unicode scalar
+//! values rather than bytes. The metric is selected with a type parameter: `Text<Levenshtein>`
+//! counts insertions, deletions and substitutions, and `Text<DamerauLevenshtein>` also counts a
+//! transposition of two adjacent characters as a single edit.
+//!
+//! Both metrics implement `Metric` for texts, so they can also be passed to
+//! `Builder::build_with_metric()`. They implement `Metric::distance_bounded()`, giving up as soon
+//! as the distance is known to exceed the bound, which makes searches much faster than computing
+//! every distance in full:
+//!
+//! ```
+//! use instant_distance::text::{Levenshtein, Text};
+//! use instant_distance::{Builder, Search};
+//!
+//! let names = ["instant", "distance", "instance", "constant"];
+//! let texts = names.iter().map(|&name| Text::<Levenshtein>::new(name)).collect();
+//! let map = Builder::default().build(texts, names.to_vec());
+//!
+//! let mut search = Search::default();
+//! let nearest = map.search(&Text::new("distanse"), &mut search).next().unwrap();
+//! assert_eq!((*nearest.value, nearest.distance), ("distance", 1.0));
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::metric::{serde_by_name, Metric};
+use crate::Point;
+
+/// A string, stored as its `char`s
+///
+/// When serialized, the text is recorded as a string.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        from = "String",
+        into = "String",
+        bound(deserialize = "M: Default", serialize = "M: Clone")
+    )
+)]
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct Text<M = Levenshtein> {
+    chars: Box<[char]>,
+    metric: M,
+}
+
+impl<M: Default> Text<M> {
+    /// Create a text from the characters of `text`
+    pub fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            metric: M::default(),
+        }
+    }
+}
+
+impl<M> Text<M> {
+    /// The characters of the text
+    pub fn chars(&self) -> &[char] {
+        &self.chars
+    }
+
+    /// The number of characters in the text
+    pub fn len(&self) -> usize {
+        self.chars.len()
+    }
+
+    /// Whether the text is empty
+    pub fn is_empty(&self) -> bool {
+        self.chars.is_empty()
+    }
+}
+
+impl<M: Default> From<&str> for Text<M> {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl<M: Default> From<String> for Text<M> {
+    fn from(text: String) -> Self {
+        Self::new(&text)
+    }
+}
+
+impl<M> From<Text<M>> for String {
+    fn from(text: Text<M>) -> Self {
+        text.chars.iter().collect()
+    }
+}
+
+impl<M> fmt::Display for Text<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.chars
+            .iter()
+            .try_for_each(|c| fmt::Write::write_char(f, *c))
+    }
+}
+
+impl<M: Metric<Self> + Clone> Point for Text<M> {
+    fn distance(&self, other: &Self) -> f32 {
+        self.metric.distance(self, other)
+    }
+
+    fn distance_bounded(&self, other: &Self, bound: f32) -> Option<f32> {
+        self.metric.distance_bounded(self, other, bound)
+    }
+}
+
+/// The minimum number of insertions, deletions and substitutions turning one text into the other
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct Levenshtein;
+
+impl<M> Metric<Text<M>> for Levenshtein {
+    fn distance(&self, a: &Text<M>, b: &Text<M>) -> f32 {
+        unbounded(levenshtein(&a.chars, &b.chars, usize::MAX))
+    }
+
+    fn distance_bounded(&self, a: &Text<M>, b: &Text<M>, bound: f32) -> Option<f32> {
+        let distance = levenshtein(&a.chars, &b.chars, whole(bound)?)?;
+        Some(distance as f32)
+    }
+}
+
+/// Like `Levenshtein`, but also counting transpositions of adjacent characters as a single edit
+///
+/// This is the unrestricted Damerau–Levenshtein distance, in which characters can be edited again
+/// after they were transposed. Unlike the simpler optimal string alignment distance, it satisfies
+/// the triangle inequality.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct DamerauLevenshtein;
+
+impl<M> Metric<Text<M>> for DamerauLevenshtein {
+    fn distance(&self, a: &Text<M>, b: &Text<M>) -> f32 {
+        unbounded(damerau_levenshtein(&a.chars, &b.chars, usize::MAX))
+    }
+
+    fn distance_bounded(&self, a: &Text<M>, b: &Text<M>, bound: f32) -> Option<f32> {
+        let distance = damerau_levenshtein(&a.chars, &b.chars, whole(bound)?)?;
+        Some(distance as f32)
+    }
+}
+
+serde_by_name!(Levenshtein, DamerauLevenshtein);
+
+/// The whole part of the distance `bound`, or `None` if no edit distance is within it
+///
+/// Edit distances are whole numbers, so only the integer part of the bound matters.
+fn whole(bound: f32) -> Option<usize> {
+    match bound >= 0.0 {
+        true => Some(bound.min(usize::MAX as f32) as usize),
+        false => None,
+    }
+}
+
+/// An edit distance computed with a bound of `usize::MAX`, which is never exceeded
+fn unbounded(distance: Option<usize>) -> f32 {
+    match distance {
+        Some(distance) => distance as f32,
+        None => unreachable!("unbounded edit distance"),
+    }
+}
+
+/// The Levenshtein distance between `a` and `b`, or `None` if it is larger than `bound`
+fn levenshtein(a: &[char], b: &[char], bound: usize) -> Option<usize> {
+    // Characters shared at the start or end don't change the distance
+    let prefix = a.iter().zip(b).take_while(|(a, b)| a == b).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a.iter().rev().zip(b.iter().rev());
+    let suffix = suffix.take_while(|(a, b)| a == b).count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    let (short, long) = match a.len() <= b.len() {
+        true => (a, b),
+        false => (b, a),
+    };
+    if long.len() - short.len() > bound {
+        return None;
+    }
+
+    // Distances from prefixes of `short` to the prefixes of `long` seen so far
+    let mut row = (0..=short.len()).collect::<Vec<_>>();
+    for (i, &c) in long.iter().enumerate() {
+        let (mut diagonal, mut min) = (row[0], i + 1);
+        row[0] = i + 1;
+        for (j, &d) in short.iter().enumerate() {
+            let substitute = diagonal + usize::from(c != d);
+            diagonal = row[j + 1];
+            row[j + 1] = Ord::min(substitute, Ord::min(row[j], row[j + 1]) + 1);
+            min = Ord::min(min, row[j + 1]);
+        }
+
+        // Every later row is at least as large as the smallest value in this one
+        if min > bound {
+            return None;
+        }
+    }
+
+    Some(row[short.len()]).filter(|&distance| distance <= bound)
+}
+
+/// The Damerau–Levenshtein distance between `a` and `b`, or `None` if it is larger than `bound`
+fn damerau_levenshtein(a: &[char], b: &[char], bound: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > bound {
+        return None;
+    }
+
+    // The distance matrix, with an extra row and column of `max` values around it
+    let (width, max) = (b.len() + 2, a.len() + b.len());
+    let mut d = vec![max; (a.len() + 2) * width];
+    for i in 0..=a.len() {
+        d[(i + 1) * width + 1] = i;
+    }
+    for j in 0..=b.len() {
+        d[width + j + 1] = j;
+    }
+
+    // The last row in which each character of `a` was seen
+    let mut last_row = HashMap::new();
+    // A lower bound for values reached by transpositions over the current row, starting from
+    // the first row, which has a minimum of zero
+    let mut skipped = 1;
+    for i in 1..=a.len() {
+        let mut last_col = 0;
+        let mut min = usize::MAX;
+        for j in 1..=b.len() {
+            let k = last_row.get(&b[j - 1]).copied().unwrap_or(0);
+            let l = last_col;
+            let cost = match a[i - 1] == b[j - 1] {
+                true => {
+                    last_col = j;
+                    0
+                }
+                false => 1,
+            };
+
+            let value = [
+                d[i * width + j] + cost,
+                d[(i + 1) * width + j] + 1,
+                d[i * width + j + 1] + 1,
+                d[k * width + l] + (i - k - 1) + 1 + (j - l - 1),
+            ]
+            .into_iter()
+            .min()
+            .unwrap();
+            d[(i + 1) * width + j + 1] = value;
+            min = Ord::min(min, value);
+        }
+        last_row.insert(a[i - 1], i);
+
+        // Later values either build on this row, or jump over it at a cost of at least one
+        // per skipped row
+        let min = Ord::min(min, i);
+        if Ord::min(min, skipped) > bound {
+            return None;
+        }
+        skipped = Ord::min(skipped, min).saturating_add(1);
+    }
+
+    Some(d[(a.len() + 1) * width + b.len() + 1]).filter(|&distance| distance <= bound)
+}
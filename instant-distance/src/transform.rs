@@ -0,0 +1,385 @@
+//! Preprocessing of dense `f32` vectors
+//!
+//! A `Pipeline` is a sequence of fitted transforms: L2 normalization, mean-centering, projection
+//! onto principal components (PCA) and Gaussian random projection. To make sure that queries get
+//! the same preprocessing as the indexed vectors, the pipeline is wrapped with a metric in a
+//! `Transformed`, which is the `Metric` of the index. The index then stores `TransformedVec`s,
+//! which can only be created by the pipeline, and the pipeline is serialized with the index.
+//! `Hnsw::search_raw()` transforms an untransformed query once with the index's pipeline, and
+//! then searches with the transformed vector:
+//!
+//! ```
+//! use instant_distance::metric::Euclidean;
+//! use instant_distance::transform::{Pipeline, Transformed};
+//! use instant_distance::{Builder, Search};
+//!
+//! let vectors = vec![vec![1.0, 2.0, 3.0], vec![2.0, 1.0, 0.0], vec![10.0, 0.0, 1.0]];
+//! let pipeline = Pipeline::new(3).normalize().center(&vectors).pca(&vectors, 2);
+//! let transformed = Transformed::new(pipeline, Euclidean);
+//!
+//! let points = vectors.iter().map(|v| transformed.transform(v)).collect();
+//! let map = Builder::default().build_with_metric(points, vec!["a", "b", "c"], transformed);
+//!
+//! let mut search = Search::default();
+//! let nearest = map.search_raw(&[20.0, 0.0, 2.0], &mut search).next().unwrap();
+//! assert_eq!(*nearest.value, "c");
+//! ```
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::metric::{Euclidean, Metric};
+use crate::store::PointStore;
+use crate::{simd, Hnsw, HnswMap, Item, MapItem, Search};
+
+/// A sequence of transforms for dense `f32` vectors
+///
+/// Each transform is fitted to the training vectors as transformed by the preceding ones. Fitting
+/// is done on all the training vectors given, so it may be worth passing a sample of a large set.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pipeline {
+    input: usize,
+    output: usize,
+    steps: Vec<Step>,
+}
+
+impl Pipeline {
+    /// Create an empty pipeline for vectors of `dimensions` values
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            input: dimensions,
+            output: dimensions,
+            steps: Vec::new(),
+        }
+    }
+
+    /// Scale vectors to a Euclidean norm of 1, leaving zero vectors as they are
+    pub fn normalize(mut self) -> Self {
+        self.steps.push(Step::Normalize);
+        self
+    }
+
+    /// Subtract the mean of `vectors`
+    pub fn center<V: AsRef<[f32]> + Sync>(mut self, vectors: &[V]) -> Self {
+        let data = self.apply_all(vectors);
+        let mean = mean(&data, self.dimensions());
+        self.steps.push(Step::Center { mean });
+        self
+    }
+
+    /// Project vectors onto the first `dimensions` principal components of `vectors`
+    ///
+    /// The vectors are centered on their mean before the projection. Panics if `dimensions` is
+    /// larger than the number of values in the vectors.
+    pub fn pca<V: AsRef<[f32]> + Sync>(mut self, vectors: &[V], dimensions: usize) -> Self {
+        let input = self.dimensions();
+        assert!(dimensions <= input, "too many principal components");
+        let data = self.apply_all(vectors);
+        let mean = mean(&data, input);
+        let components = principal_components(&data, &mean, dimensions);
+        self.steps.push(Step::Project {
+            mean: Some(mean),
+            matrix: components,
+        });
+        self.output = dimensions;
+        self
+    }
+
+    /// Project vectors onto `dimensions` random directions, with values drawn from `seed`
+    ///
+    /// The matrix has independent Gaussian values with variance `1 / dimensions`, so that
+    /// Euclidean distances are preserved in expectation.
+    pub fn random_projection(mut self, dimensions: usize, seed: u64) -> Self {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let scale = (dimensions as f32).sqrt().recip();
+        let matrix = (0..dimensions * self.dimensions())
+            .map(|_| gaussian(&mut rng) * scale)
+            .collect();
+        self.steps.push(Step::Project { mean: None, matrix });
+        self.output = dimensions;
+        self
+    }
+
+    /// Apply the transforms to `vector`
+    ///
+    /// Panics if the length of `vector` differs from the pipeline's input dimensionality.
+    pub fn apply(&self, vector: &[f32]) -> Vec<f32> {
+        assert_eq!(vector.len(), self.input, "vector length mismatch");
+        let mut vector = vector.to_vec();
+        for step in &self.steps {
+            vector = step.apply(vector);
+        }
+        vector
+    }
+
+    /// The number of values in the input vectors
+    pub fn input_dimensions(&self) -> usize {
+        self.input
+    }
+
+    /// The number of values in the transformed vectors
+    pub fn dimensions(&self) -> usize {
+        self.output
+    }
+
+    fn apply_all<V: AsRef<[f32]> + Sync>(&self, vectors: &[V]) -> Vec<Vec<f32>> {
+        vectors.par_iter().map(|v| self.apply(v.as_ref())).collect()
+    }
+}
+
+/// A fitted transform
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+enum Step {
+    Normalize,
+    Center {
+        mean: Vec<f32>,
+    },
+    /// Multiplication by a matrix with one row per output value, after subtracting `mean`
+    Project {
+        mean: Option<Vec<f32>>,
+        matrix: Vec<f32>,
+    },
+}
+
+impl Step {
+    fn apply(&self, mut vector: Vec<f32>) -> Vec<f32> {
+        match self {
+            Self::Normalize => {
+                let norm = simd::dot(&vector, &vector).sqrt();
+                if norm > 0.0 {
+                    vector.iter_mut().for_each(|x| *x /= norm);
+                }
+                vector
+            }
+            Self::Center { mean } => {
+                vector.iter_mut().zip(mean).for_each(|(x, m)| *x -= m);
+                vector
+            }
+            Self::Project { mean, matrix } => {
+                if let Some(mean) = mean {
+                    vector.iter_mut().zip(mean).for_each(|(x, m)| *x -= m);
+                }
+                matrix
+                    .chunks_exact(vector.len())
+                    .map(|row| simd::dot(row, &vector))
+                    .collect()
+            }
+        }
+    }
+}
+
+/// A metric for vectors transformed by a `Pipeline`
+///
+/// Distances between `TransformedVec`s are computed by the wrapped metric. Use `transform()` to
+/// create the points to index and the queries, or search with `Hnsw::search_raw()`.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Transformed<M = Euclidean> {
+    pipeline: Pipeline,
+    metric: M,
+}
+
+impl<M> Transformed<M> {
+    /// Compare vectors transformed by `pipeline` using `metric`
+    pub fn new(pipeline: Pipeline, metric: M) -> Self {
+        Self { pipeline, metric }
+    }
+
+    /// Transform `vector` for indexing or searching
+    pub fn transform(&self, vector: &[f32]) -> TransformedVec {
+        TransformedVec(self.pipeline.apply(vector).into())
+    }
+
+    /// The pipeline applied to vectors
+    pub fn pipeline(&self) -> &Pipeline {
+        &self.pipeline
+    }
+}
+
+impl<M: Metric> Metric<TransformedVec> for Transformed<M> {
+    fn distance(&self, a: &TransformedVec, b: &TransformedVec) -> f32 {
+        self.metric.distance(&a.0, &b.0)
+    }
+
+    fn distance_bounded(&self, a: &TransformedVec, b: &TransformedVec, bound: f32) -> Option<f32> {
+        self.metric.distance_bounded(&a.0, &b.0, bound)
+    }
+}
+
+impl<M: Metric, S: PointStore<Point = TransformedVec>> Hnsw<TransformedVec, Transformed<M>, S> {
+    /// Search the index for the points nearest to the untransformed vector `query`
+    ///
+    /// The query is transformed once by the index's pipeline before searching.
+    pub fn search_raw<'a, 'b: 'a>(
+        &'b self,
+        query: &[f32],
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = Item<'b, TransformedVec>> + 'a {
+        self.search(&self.metric.transform(query), search)
+    }
+}
+
+impl<V, M, S> HnswMap<TransformedVec, V, Transformed<M>, S>
+where
+    V: Clone,
+    M: Metric,
+    S: PointStore<Point = TransformedVec>,
+{
+    /// Search the index for the points nearest to the untransformed vector `query`
+    ///
+    /// See `Hnsw::search_raw()` for details.
+    pub fn search_raw<'a>(
+        &'a self,
+        query: &[f32],
+        search: &'a mut Search,
+    ) -> impl ExactSizeIterator<Item = MapItem<'a, TransformedVec, V>> + 'a {
+        self.hnsw
+            .search_raw(query, search)
+            .map(move |item| MapItem::from(item, self))
+    }
+}
+
+/// A vector transformed by `Transformed::transform()`
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransformedVec(Box<[f32]>);
+
+impl TransformedVec {
+    /// The transformed values
+    pub fn as_slice(&self) -> &[f32] {
+        &self.0
+    }
+}
+
+impl AsRef<[f32]> for TransformedVec {
+    fn as_ref(&self) -> &[f32] {
+        &self.0
+    }
+}
+
+/// The mean of `data`, which contains vectors of `dimensions` values
+fn mean(data: &[Vec<f32>], dimensions: usize) -> Vec<f32> {
+    let mut sum = vec![0.0f64; dimensions];
+    for vector in data {
+        sum.iter_mut()
+            .zip(vector)
+            .for_each(|(s, &x)| *s += f64::from(x));
+    }
+
+    let len = data.len().max(1) as f64;
+    sum.into_iter().map(|s| (s / len) as f32).collect()
+}
+
+/// The `k` eigenvectors of the covariance matrix of `data` with the largest eigenvalues
+///
+/// The eigenvectors are found by subspace iteration, and returned as the rows of a matrix.
+fn principal_components(data: &[Vec<f32>], mean: &[f32], k: usize) -> Vec<f32> {
+    let d = mean.len();
+    let covariance = data
+        .par_iter()
+        .fold(
+            || vec![0.0f64; d * d],
+            |mut sum, vector| {
+                let centered = vector
+                    .iter()
+                    .zip(mean)
+                    .map(|(x, m)| f64::from(x - m))
+                    .collect::<Vec<_>>();
+                for (i, &x) in centered.iter().enumerate() {
+                    let row = &mut sum[i * d..(i + 1) * d];
+                    row.iter_mut().zip(&centered).for_each(|(s, y)| *s += x * y);
+                }
+                sum
+            },
+        )
+        .reduce(
+            || vec![0.0f64; d * d],
+            |mut a, b| {
+                a.iter_mut().zip(b).for_each(|(a, b)| *a += b);
+                a
+            },
+        );
+
+    // Start from random directions, so that no component is orthogonal to the initial subspace
+    let mut rng = SmallRng::seed_from_u64(0);
+    let mut basis = (0..k * d)
+        .map(|_| f64::from(gaussian(&mut rng)))
+        .collect::<Vec<_>>();
+    orthonormalize(&mut basis, d);
+    for _ in 0..PCA_ITERATIONS {
+        let mut next = basis
+            .chunks_exact(d)
+            .flat_map(|v| covariance.chunks_exact(d).map(move |row| dot(row, v)))
+            .collect::<Vec<_>>();
+        orthonormalize(&mut next, d);
+
+        let change = basis
+            .iter()
+            .zip(&next)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max);
+        basis = next;
+        if change < PCA_TOLERANCE {
+            break;
+        }
+    }
+
+    // Order the components by their variance, the Rayleigh quotient of the covariance matrix
+    let mut components = basis
+        .chunks_exact(d)
+        .map(|v| {
+            let cv = covariance.chunks_exact(d).map(|row| dot(row, v));
+            let variance = cv.zip(v).map(|(a, b)| a * b).sum::<f64>();
+            (variance, v)
+        })
+        .collect::<Vec<_>>();
+    components.sort_by(|a, b| b.0.total_cmp(&a.0));
+    components
+        .into_iter()
+        .flat_map(|(_, v)| v.iter().map(|&x| x as f32))
+        .collect()
+}
+
+/// Make the rows of `matrix`, of `d` values each, orthonormal using modified Gram-Schmidt
+///
+/// Rows that are linearly dependent on the preceding rows are set to zero.
+fn orthonormalize(matrix: &mut [f64], d: usize) {
+    for i in 0..matrix.len() / d {
+        let (done, rest) = matrix.split_at_mut(i * d);
+        let row = &mut rest[..d];
+        for prev in done.chunks_exact(d) {
+            let projection = dot(prev, row);
+            row.iter_mut()
+                .zip(prev)
+                .for_each(|(x, p)| *x -= projection * p);
+        }
+
+        let norm = dot(row, row).sqrt();
+        match norm > f64::EPSILON {
+            true => row.iter_mut().for_each(|x| *x /= norm),
+            false => row.iter_mut().for_each(|x| *x = 0.0),
+        }
+    }
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// A value from the standard normal distribution, using the Box-Muller transform
+fn gaussian(rng: &mut SmallRng) -> f32 {
+    let u = 1.0 - rng.random::<f32>();
+    let v = rng.random::<f32>();
+    (-2.0 * u.ln()).sqrt() * (std::f32::consts::TAU * v).cos()
+}
+
+/// The maximum number of subspace iterations used to find principal components
+const PCA_ITERATIONS: usize = 200;
+
+/// The largest change in the principal components at which subspace iteration stops
+const PCA_TOLERANCE: f64 = 1e-9;
@@ -8,7 +8,8 @@ use serde::{Deserialize, Serialize};
 #[cfg(feature = "serde-big-array")]
 use serde_big_array::BigArray;
 
-use crate::{Hnsw, Point, M};
+use crate::store::PointStore;
+use crate::{Hnsw, M};
 
 pub(crate) struct Visited {
     store: Vec<u8>,
@@ -68,6 +69,12 @@ impl UpperNode {
         nearest.copy_from_slice(&node.0[..M]);
         Self(nearest)
     }
+
+    pub(crate) fn remap(&mut self, new: &[PointId]) {
+        for pid in self.0.iter_mut().take_while(|pid| pid.is_valid()) {
+            *pid = new[pid.0 as usize];
+        }
+    }
 }
 
 impl<'a> Layer for &'a [UpperNode] {
@@ -97,23 +104,14 @@ impl ZeroNode {
         }
     }
 
-    pub(crate) fn insert(&mut self, idx: usize, pid: PointId) {
-        // It might be possible for all the neighbor's current neighbors to be closer to our
-        // neighbor than to the new node, in which case we skip insertion of our new node's ID.
-        if idx >= self.0.len() {
-            return;
-        }
-
-        if self.0[idx].is_valid() {
-            let end = (M * 2) - 1;
-            self.0.copy_within(idx..end, idx + 1);
-        }
-
+    pub(crate) fn set(&mut self, idx: usize, pid: PointId) {
         self.0[idx] = pid;
     }
 
-    pub(crate) fn set(&mut self, idx: usize, pid: PointId) {
-        self.0[idx] = pid;
+    pub(crate) fn remap(&mut self, new: &[PointId]) {
+        for pid in self.0.iter_mut().take_while(|pid| pid.is_valid()) {
+            *pid = new[pid.0 as usize];
+        }
     }
 }
 
@@ -233,6 +231,21 @@ pub struct Candidate {
     pub pid: PointId,
 }
 
+impl Candidate {
+    /// Create a candidate for `pid` at the given `distance` from the base point
+    pub fn new(pid: PointId, distance: f32) -> Self {
+        Self {
+            distance: OrderedFloat::from(distance),
+            pid,
+        }
+    }
+
+    /// The distance from the base point to this candidate
+    pub fn distance(&self) -> f32 {
+        self.distance.into_inner()
+    }
+}
+
 /// References a `Point` in the `Hnsw`
 ///
 /// This can be used to index into the `Hnsw` to refer to the `Point` data.
@@ -266,24 +279,16 @@ impl Default for PointId {
     }
 }
 
-impl<P> Index<PointId> for Hnsw<P> {
+impl<P: ?Sized, D, S: PointStore<Point = P>> Index<PointId> for Hnsw<P, D, S> {
     type Output = P;
 
     fn index(&self, index: PointId) -> &Self::Output {
-        &self.points[index.0 as usize]
-    }
-}
-
-impl<P: Point> Index<PointId> for [P] {
-    type Output = P;
-
-    fn index(&self, index: PointId) -> &Self::Output {
-        &self[index.0 as usize]
+        self.points.point(index)
     }
 }
 
-impl Index<PointId> for [RwLock<ZeroNode>] {
-    type Output = RwLock<ZeroNode>;
+impl<T> Index<PointId> for [T] {
+    type Output = T;
 
     fn index(&self, index: PointId) -> &Self::Output {
         &self[index.0 as usize]
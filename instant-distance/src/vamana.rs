@@ -0,0 +1,259 @@
+use std::collections::HashSet;
+use std::marker::PhantomData;
+#[cfg(feature = "indicatif")]
+use std::sync::atomic::{self, AtomicUsize};
+
+#[cfg(feature = "indicatif")]
+use indicatif::ProgressBar;
+use parking_lot::RwLock;
+use rand::rngs::SmallRng;
+use rand::seq::{index, SliceRandom};
+use rand::SeedableRng;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::metric::Metric;
+use crate::store::{Permute, PointStore};
+use crate::types::{Layer, ZeroNode, INVALID};
+use crate::{
+    approximate_medoid, permute, Builder, EntryPoint, Heuristic, Hnsw, PointId, QueryDistances,
+    SearchPool, Space, M,
+};
+
+/// Parameters for building a flat (single layer) graph as described in the DiskANN paper
+///
+/// Instead of a hierarchy of layers, the resulting `Hnsw` has a single layer which is searched
+/// starting from an approximate medoid of the points. The graph is constructed in two passes
+/// over all points, first pruning neighbors with an `alpha` of 1.0 and then with the configured
+/// `alpha`, which adds the long-range links needed to navigate a flat graph efficiently.
+///
+/// The neighbors of each point are pruned from all nodes expanded while searching the graph for
+/// it, as in the paper. The builder's `ef_construction` is used as the search list size (`L` in
+/// the paper); the `ml` parameter and neighbor selection strategy do not apply to flat graphs.
+/// Unless an explicit entry point is configured, the approximate medoid of all points is used.
+#[derive(Copy, Clone, Debug)]
+pub struct Vamana {
+    /// Relaxation factor for pruning neighbors in the second pass
+    pub alpha: f32,
+    /// Maximum number of neighbors per point (`R` in the paper), limited to 64
+    pub max_degree: usize,
+}
+
+impl Default for Vamana {
+    fn default() -> Self {
+        Self {
+            alpha: 1.2,
+            max_degree: M * 2,
+        }
+    }
+}
+
+pub(crate) fn build<S: Permute, D: Metric<S::Point>>(
+    mut points: S,
+    metric: D,
+    builder: Builder,
+    params: Vamana,
+) -> (Hnsw<S::Point, D, S>, Vec<PointId>) {
+    let mut rng = SmallRng::seed_from_u64(builder.seed);
+
+    #[cfg(feature = "indicatif")]
+    let progress = builder.progress;
+    #[cfg(feature = "indicatif")]
+    if let Some(bar) = &progress {
+        bar.set_length(2 * points.len() as u64);
+        bar.set_message("Build index (preparation)");
+    }
+
+    if points.is_empty() {
+        return (Hnsw::empty(builder.ef_search, points, metric), Vec::new());
+    }
+
+    // Put the points in random order, except for the medoid, which goes first so that it is
+    // used as the entry point for all searches.
+
+    assert!(points.len() < u32::MAX as usize);
+    let medoid = match builder.entry_point {
+        Some(EntryPoint::Point(idx)) => idx,
+        Some(EntryPoint::Medoid) | None => {
+            let point = |i: usize| points.point(PointId(i as u32));
+            let distance = |i: usize, j: usize| metric.distance(point(i), point(j));
+            approximate_medoid(points.len(), distance, &mut rng)
+        }
+    };
+    let mut order = (0..points.len()).collect::<Vec<_>>();
+    order.shuffle(&mut rng);
+    let first = order
+        .iter()
+        .position(|&i| i == medoid)
+        .expect("entry point out of range");
+    order.swap(0, first);
+
+    let mut out = vec![INVALID; points.len()];
+    for (i, idx) in order.into_iter().enumerate() {
+        out[idx] = PointId(i as u32);
+    }
+    permute(&mut points, &out);
+
+    // Start from a random graph, in which every node has `max_degree` neighbors.
+
+    let max_degree = params.max_degree.clamp(1, M * 2);
+    let degree = Ord::min(max_degree, points.len() - 1);
+    let zero = (0..points.len())
+        .map(|i| {
+            let mut node = ZeroNode::default();
+            let neighbors = index::sample(&mut rng, points.len() - 1, degree)
+                .into_iter()
+                .map(|j| PointId(if j >= i { j + 1 } else { j } as u32));
+            node.rewrite(neighbors);
+            RwLock::new(node)
+        })
+        .collect::<Vec<_>>();
+
+    let mut state = Construction {
+        zero: &zero,
+        pool: SearchPool::new(points.len()),
+        space: Space {
+            points: &points,
+            metric: &metric,
+        },
+        heuristic: Heuristic {
+            extend_candidates: false,
+            keep_pruned: false,
+            alpha: 1.0,
+        },
+        max_degree,
+        ef_construction: builder.ef_construction,
+        #[cfg(feature = "indicatif")]
+        progress,
+        #[cfg(feature = "indicatif")]
+        done: AtomicUsize::new(0),
+    };
+
+    for alpha in [1.0, params.alpha] {
+        #[cfg(feature = "indicatif")]
+        if let Some(bar) = &state.progress {
+            bar.set_message(format!("Building index (alpha = {alpha})"));
+        }
+
+        state.heuristic.alpha = alpha;
+        let mut order = (0..points.len() as u32).map(PointId).collect::<Vec<_>>();
+        order.shuffle(&mut rng);
+        order.into_par_iter().for_each(|pid| state.insert(pid));
+    }
+
+    #[cfg(feature = "indicatif")]
+    if let Some(bar) = &state.progress {
+        bar.finish();
+    }
+
+    (
+        Hnsw {
+            ef_search: builder.ef_search,
+            zero: zero.into_iter().map(|node| node.into_inner()).collect(),
+            points,
+            layers: Vec::new(),
+            metric,
+            point: PhantomData,
+        },
+        out,
+    )
+}
+
+struct Construction<'a, S, D> {
+    zero: &'a [RwLock<ZeroNode>],
+    pool: SearchPool,
+    space: Space<'a, S, D>,
+    heuristic: Heuristic,
+    max_degree: usize,
+    ef_construction: usize,
+    #[cfg(feature = "indicatif")]
+    progress: Option<ProgressBar>,
+    #[cfg(feature = "indicatif")]
+    done: AtomicUsize,
+}
+
+impl<S: PointStore, D: Metric<S::Point>> Construction<'_, S, D> {
+    /// Update the neighbors of node `pid` (the loop body of algorithm 1 in the DiskANN paper)
+    ///
+    /// Searches the graph for the point, prunes the expanded and nearest nodes together with the
+    /// node's current neighbors to at most `max_degree` neighbors, and adds backward links from
+    /// each of the selected neighbors, pruning their neighbors if they have too many.
+    fn insert(&self, pid: PointId) {
+        let (mut search, mut insertion) = self.pool.pop();
+        insertion.ef = self.ef_construction;
+
+        let distances = QueryDistances {
+            query: self.space.points.point(pid),
+            space: self.space,
+        };
+
+        search.expanded.get_or_insert_with(Vec::new);
+        search.reset();
+        search.ef = self.ef_construction;
+        search.push(PointId(0), &distances);
+        search.search(&distances, self.zero, M * 2);
+
+        // Prune all nodes expanded by the search (`V` in the paper), not only the nearest ones,
+        // together with the current neighbors; the candidates should never include the point
+        search.ef = usize::MAX;
+        let expanded = search.expanded.take().unwrap_or_default();
+        for &candidate in &expanded {
+            if let Err(idx) = search.nearest.binary_search(&candidate) {
+                search.nearest.insert(idx, candidate);
+            }
+        }
+        search.expanded = Some(expanded);
+        for neighbor in self.zero.nearest_iter(pid) {
+            search.push(neighbor, &distances);
+        }
+        search.nearest.retain(|candidate| candidate.pid != pid);
+
+        let found = search.select(pid, self.zero, self.space, &self.heuristic, self.max_degree);
+
+        // Just make sure the candidates are all unique
+        debug_assert_eq!(
+            found.len(),
+            found.iter().map(|c| c.pid).collect::<HashSet<_>>().len()
+        );
+
+        self.zero[pid]
+            .write()
+            .rewrite(found.iter().map(|candidate| candidate.pid));
+
+        for candidate in found {
+            let neighbor = candidate.pid;
+            {
+                let mut node = self.zero[neighbor].write();
+                let len = node.iter().take_while(|pid| pid.is_valid()).count();
+                if node[..len].contains(&pid) {
+                    continue;
+                } else if len < self.max_degree {
+                    node.set(len, pid);
+                    continue;
+                }
+            }
+
+            let found = insertion.add_neighbor(
+                pid,
+                neighbor,
+                self.zero,
+                self.space,
+                &self.heuristic,
+                self.max_degree,
+            );
+
+            self.zero[neighbor]
+                .write()
+                .rewrite(found.iter().map(|candidate| candidate.pid));
+        }
+
+        #[cfg(feature = "indicatif")]
+        if let Some(bar) = &self.progress {
+            let value = self.done.fetch_add(1, atomic::Ordering::Relaxed);
+            if value.is_multiple_of(1000) {
+                bar.set_position(value as u64);
+            }
+        }
+
+        self.pool.push((search, insertion));
+    }
+}
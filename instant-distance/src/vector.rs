@@ -0,0 +1,259 @@
+//! Dense vector types implementing `Point`
+//!
+//! The distance metric is selected with a type parameter, using one of the types from the
+//! `metric` module. For example, `FloatArray<300, Cosine>` is a 300-dimensional vector compared
+//! by cosine distance.
+//!
+//! With the `half` feature, `F16Vec` and `Bf16Vec` store vectors at half precision. Their
+//! values are converted to `f32` to compute distances, using F16C instructions where available.
+
+#[cfg(feature = "half")]
+use std::cell::RefCell;
+use std::ops::Deref;
+
+#[cfg(feature = "half")]
+use half::{bf16, f16, slice::HalfFloatSliceExt};
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde-big-array")]
+use serde_big_array::BigArray;
+
+use crate::metric::{Euclidean, Metric};
+use crate::Point;
+
+/// A fixed-size dense vector
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FloatArray<const N: usize, M = Euclidean> {
+    #[cfg_attr(feature = "serde", serde(with = "BigArray"))]
+    array: [f32; N],
+    #[cfg_attr(feature = "serde", serde(skip))]
+    metric: M,
+}
+
+impl<const N: usize, M: Default> FloatArray<N, M> {
+    /// Create a new vector from the given `array`
+    pub fn new(array: [f32; N]) -> Self {
+        Self {
+            array,
+            metric: M::default(),
+        }
+    }
+
+    /// Return the vector's elements
+    pub fn into_inner(self) -> [f32; N] {
+        self.array
+    }
+}
+
+impl<const N: usize, M: Metric + Clone> Point for FloatArray<N, M> {
+    fn distance(&self, other: &Self) -> f32 {
+        self.metric.distance(&self.array, &other.array)
+    }
+
+    fn distance_bounded(&self, other: &Self, bound: f32) -> Option<f32> {
+        self.metric
+            .distance_bounded(&self.array, &other.array, bound)
+    }
+}
+
+impl<const N: usize, M: Default> From<[f32; N]> for FloatArray<N, M> {
+    fn from(array: [f32; N]) -> Self {
+        Self::new(array)
+    }
+}
+
+impl<const N: usize, M> Deref for FloatArray<N, M> {
+    type Target = [f32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.array
+    }
+}
+
+impl<const N: usize, M> AsRef<[f32]> for FloatArray<N, M> {
+    fn as_ref(&self) -> &[f32] {
+        &self.array
+    }
+}
+
+/// A dense vector with a dimensionality chosen at runtime
+///
+/// All vectors in an index must have the same length.
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FloatVec<M = Euclidean> {
+    vector: Box<[f32]>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    metric: M,
+}
+
+impl<M: Default> FloatVec<M> {
+    /// Create a new vector from the given `vector`
+    pub fn new(vector: impl Into<Box<[f32]>>) -> Self {
+        Self {
+            vector: vector.into(),
+            metric: M::default(),
+        }
+    }
+
+    /// Return the vector's elements
+    pub fn into_inner(self) -> Box<[f32]> {
+        self.vector
+    }
+}
+
+impl<M: Metric + Clone> Point for FloatVec<M> {
+    fn distance(&self, other: &Self) -> f32 {
+        self.metric.distance(&self.vector, &other.vector)
+    }
+
+    fn distance_bounded(&self, other: &Self, bound: f32) -> Option<f32> {
+        self.metric
+            .distance_bounded(&self.vector, &other.vector, bound)
+    }
+}
+
+impl<M: Default> From<Vec<f32>> for FloatVec<M> {
+    fn from(vector: Vec<f32>) -> Self {
+        Self::new(vector)
+    }
+}
+
+impl<M: Default> From<Box<[f32]>> for FloatVec<M> {
+    fn from(vector: Box<[f32]>) -> Self {
+        Self::new(vector)
+    }
+}
+
+impl<M> Deref for FloatVec<M> {
+    type Target = [f32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.vector
+    }
+}
+
+impl<M> AsRef<[f32]> for FloatVec<M> {
+    fn as_ref(&self) -> &[f32] {
+        &self.vector
+    }
+}
+
+/// Define a dense vector type storing half-precision values of type `$elem`
+#[cfg(feature = "half")]
+macro_rules! half_vector {
+    ($(#[$meta:meta])* $name:ident, $elem:ty) => {
+        $(#[$meta])*
+        #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+        #[derive(Clone, Debug, PartialEq)]
+        pub struct $name<M = Euclidean> {
+            vector: Box<[$elem]>,
+            #[cfg_attr(feature = "serde", serde(skip))]
+            metric: M,
+        }
+
+        impl<M: Default> $name<M> {
+            /// Create a new vector from the given `vector`
+            pub fn new(vector: impl Into<Box<[$elem]>>) -> Self {
+                Self {
+                    vector: vector.into(),
+                    metric: M::default(),
+                }
+            }
+
+            /// Create a new vector by rounding the values in `vector`
+            pub fn from_f32(vector: &[f32]) -> Self {
+                let mut new = vec![<$elem>::ZERO; vector.len()];
+                new.convert_from_f32_slice(vector);
+                Self::new(new)
+            }
+
+            /// Return the vector's elements
+            pub fn into_inner(self) -> Box<[$elem]> {
+                self.vector
+            }
+        }
+
+        impl<M> $name<M> {
+            /// Convert the vector's elements to `f32`
+            pub fn to_f32(&self) -> Vec<f32> {
+                self.vector.to_f32_vec()
+            }
+        }
+
+        impl<M: Metric + Clone> Point for $name<M> {
+            fn distance(&self, other: &Self) -> f32 {
+                with_f32(&self.vector[..], &other.vector[..], |a, b| self.metric.distance(a, b))
+            }
+
+            fn distance_bounded(&self, other: &Self, bound: f32) -> Option<f32> {
+                with_f32(&self.vector[..], &other.vector[..], |a, b| {
+                    self.metric.distance_bounded(a, b, bound)
+                })
+            }
+        }
+
+        impl<M: Default> From<Vec<$elem>> for $name<M> {
+            fn from(vector: Vec<$elem>) -> Self {
+                Self::new(vector)
+            }
+        }
+
+        impl<M> Deref for $name<M> {
+            type Target = [$elem];
+
+            fn deref(&self) -> &Self::Target {
+                &self.vector
+            }
+        }
+
+        impl<M> AsRef<[$elem]> for $name<M> {
+            fn as_ref(&self) -> &[$elem] {
+                &self.vector
+            }
+        }
+    };
+}
+
+#[cfg(feature = "half")]
+half_vector!(
+    /// A dense vector of IEEE 754 half-precision (`f16`) values
+    ///
+    /// All vectors in an index must have the same length.
+    F16Vec,
+    f16
+);
+
+#[cfg(feature = "half")]
+half_vector!(
+    /// A dense vector of bfloat16 (`bf16`) values
+    ///
+    /// All vectors in an index must have the same length.
+    Bf16Vec,
+    bf16
+);
+
+/// Call `f` with the vectors `a` and `b` converted to `f32`
+///
+/// Uses buffers kept per thread to avoid allocating for every distance computation.
+#[cfg(feature = "half")]
+fn with_f32<T, R>(a: &[T], b: &[T], f: impl FnOnce(&[f32], &[f32]) -> R) -> R
+where
+    [T]: HalfFloatSliceExt,
+{
+    thread_local! {
+        static BUFFERS: RefCell<(Vec<f32>, Vec<f32>)> =
+            const { RefCell::new((Vec::new(), Vec::new())) };
+    }
+
+    BUFFERS.with(|buffers| {
+        let (a_buf, b_buf) = &mut *buffers.borrow_mut();
+        a_buf.resize(a.len(), 0.0);
+        b_buf.resize(b.len(), 0.0);
+        a.convert_to_f32_slice(a_buf);
+        b.convert_to_f32_slice(b_buf);
+        f(a_buf, b_buf)
+    })
+}
@@ -1,10 +1,34 @@
 use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 
 use ordered_float::OrderedFloat;
 use rand::rngs::{StdRng, ThreadRng};
 use rand::{Rng, SeedableRng};
 
-use instant_distance::{Builder, Point as _, Search};
+use instant_distance::binary::{BitArray, BitVec};
+use instant_distance::geo::{Haversine, LatLng, Vincenty};
+use instant_distance::kmeans::KMeans;
+use instant_distance::metric::{
+    Chebyshev, Cosine, Euclidean, Hamming, InnerProduct, Manhattan, Metric as _, PointMetric,
+    SquaredEuclidean,
+};
+use instant_distance::mips::Mips;
+use instant_distance::pq::ProductQuantizer;
+use instant_distance::quantize::{Granularity, ScalarQuantizer};
+use instant_distance::set::{MinHasher, SortedSet};
+use instant_distance::simd::{self, Level};
+use instant_distance::sparse::SparseVec;
+use instant_distance::store::{Arena, Flat, Lazy, Mapped, PointStore};
+use instant_distance::text::{DamerauLevenshtein, Levenshtein, Text};
+use instant_distance::transform::{Pipeline, Transformed};
+#[cfg(feature = "half")]
+use instant_distance::vector::{Bf16Vec, F16Vec};
+use instant_distance::vector::{FloatArray, FloatVec};
+use instant_distance::{
+    Builder, Candidate, EntryPoint, Graph, Heuristic, NeighborSelector, Point as _, PointId,
+    Reorder, Search, Vamana,
+};
 
 #[test]
 #[allow(clippy::float_cmp, clippy::approx_constant)]
@@ -45,6 +69,17 @@ fn random_heuristic() {
     assert!(recall > 97, "expected at least 98, got {recall}");
 }
 
+#[test]
+fn random_heuristic_alpha() {
+    let heuristic = Heuristic {
+        alpha: 1.2,
+        ..Heuristic::default()
+    };
+    let (seed, recall) = randomized(Builder::default().select_heuristic(Some(heuristic)));
+    println!("heuristic alpha (seed = {seed}) recall = {recall}");
+    assert!(recall > 97, "expected at least 98, got {recall}");
+}
+
 #[test]
 fn random_simple() {
     let (seed, recall) = randomized(Builder::default().select_heuristic(None));
@@ -52,6 +87,1159 @@ fn random_simple() {
     assert!(recall > 90, "expected at least 90, got {recall}");
 }
 
+#[test]
+fn random_medoid() {
+    let (seed, recall) = randomized(Builder::default().entry_point(Some(EntryPoint::Medoid)));
+    println!("medoid (seed = {seed}) recall = {recall}");
+    assert!(recall > 97, "expected at least 98, got {recall}");
+}
+
+#[test]
+fn entry_points() {
+    let seed = ThreadRng::default().random();
+    println!("entry points (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..1024)
+        .map(|_| Point(rng.random(), rng.random()))
+        .collect::<Vec<_>>();
+
+    let query = Point(rng.random(), rng.random());
+    let nearest = points
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| OrderedFloat::from(query.distance(p)))
+        .map(|(i, _)| i)
+        .unwrap();
+
+    let (hnsw, pids) = Builder::default()
+        .seed(seed)
+        .entry_point(Some(EntryPoint::Point(7)))
+        .build_hnsw(points);
+    assert_eq!(pids[7].into_inner(), 0);
+
+    let entries = [pids[1], pids[2], pids[3]];
+    let mut search = Search::default();
+    let first = hnsw.search_from(&query, &entries, &mut search).next();
+    assert_eq!(first.unwrap().pid, pids[nearest]);
+
+    // Entry points outside of the index are ignored instead of panicking
+    let entries = [PointId::from(1024), PointId::default()];
+    let first = hnsw.search_from(&query, &entries, &mut search).next();
+    assert_eq!(first.unwrap().pid, pids[nearest]);
+}
+
+#[test]
+fn random_reorder() {
+    for strategy in [Reorder::Bfs, Reorder::ReverseCuthillMcKee] {
+        let (seed, recall) = randomized(Builder::default().reorder(Some(strategy)));
+        println!("reorder {strategy:?} (seed = {seed}) recall = {recall}");
+        assert!(recall > 97, "expected at least 98, got {recall}");
+
+        let points = (0..256)
+            .map(|i| Point(i as f32, (i % 16) as f32))
+            .collect::<Vec<_>>();
+        let map = Builder::default()
+            .seed(seed)
+            .reorder(Some(strategy))
+            .build(points.clone(), points);
+        let mut search = Search::default();
+        for item in map.search(&Point(128.0, 8.0), &mut search) {
+            assert_eq!((item.point.0, item.point.1), (item.value.0, item.value.1));
+        }
+    }
+}
+
+#[test]
+fn random_vamana() {
+    let (seed, recall) = randomized(Builder::default().vamana(Some(Vamana::default())));
+    println!("vamana (seed = {seed}) recall = {recall}");
+    assert!(recall > 97, "expected at least 98, got {recall}");
+    // With a short search list, pruning relies on the other nodes expanded by the search
+    let builder = Builder::default().ef_construction(16);
+    let (seed, recall) = randomized(builder.vamana(Some(Vamana::default())));
+    println!("vamana ef 16 (seed = {seed}) recall = {recall}");
+    assert!(recall > 90, "expected at least 90, got {recall}");
+}
+
+#[test]
+fn random_custom_selector() {
+    let (seed, recall) = randomized(Builder::default().neighbor_selector(Capped(16)));
+    println!("custom selector (seed = {seed}) recall = {recall}");
+    assert!(recall > 90, "expected at least 90, got {recall}");
+}
+
+/// Selects at most the given number of neighbors, preferring candidates not yet covered
+struct Capped(usize);
+
+impl NeighborSelector for Capped {
+    fn select(
+        &self,
+        candidates: &mut Vec<Candidate>,
+        max: usize,
+        graph: &mut dyn Graph,
+        selected: &mut Vec<Candidate>,
+    ) {
+        let max = Ord::min(max, self.0);
+        for candidate in candidates.iter() {
+            if selected.len() >= max {
+                break;
+            }
+
+            let covered = selected
+                .iter()
+                .any(|result| graph.distance(candidate.pid, result.pid) < candidate.distance());
+            if !covered {
+                selected.push(*candidate);
+            }
+        }
+    }
+}
+
+#[test]
+fn random_unruly_selector() {
+    let (seed, recall) = randomized(Builder::default().neighbor_selector(Unruly));
+    println!("unruly selector (seed = {seed}) recall = {recall}");
+    assert!(recall > 90, "expected at least 90, got {recall}");
+}
+
+/// Breaks the selector contract, selecting the base point and every candidate twice
+struct Unruly;
+
+impl NeighborSelector for Unruly {
+    fn select(
+        &self,
+        candidates: &mut Vec<Candidate>,
+        _: usize,
+        graph: &mut dyn Graph,
+        selected: &mut Vec<Candidate>,
+    ) {
+        selected.push(Candidate::new(graph.base(), 0.0));
+        for candidate in candidates.iter() {
+            selected.extend([*candidate, *candidate]);
+        }
+    }
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn metrics() {
+    let (a, b) = ([1.0, 2.0, 3.0], [4.0, -2.0, 3.0]);
+    assert_eq!(Euclidean.distance(&a, &b), 5.0);
+    assert_eq!(SquaredEuclidean.distance(&a, &b), 25.0);
+    assert_eq!(Manhattan.distance(&a, &b), 7.0);
+    assert_eq!(Chebyshev.distance(&a, &b), 4.0);
+    assert_eq!(InnerProduct.distance(&a, &b), -9.0);
+
+    let cosine = 1.0 - 9.0 / (14.0f32 * 29.0).sqrt();
+    assert!((Cosine.distance(&a, &b) - cosine).abs() < 1e-6);
+    assert_eq!(Cosine.distance(&a, &a), 0.0);
+    assert_eq!(Cosine.distance(&a, &[0.0; 3]), 1.0);
+    assert!((Cosine.distance(&a, &[-2.0, -4.0, -6.0]) - 2.0).abs() < 1e-6);
+}
+
+#[test]
+#[allow(clippy::float_cmp)]
+fn bounded_distances() {
+    let (a, mut b) = (vec![0.0; 256], vec![1.0; 256]);
+    b[200] = 3.0;
+    b[201..209].fill(0.0);
+    assert_eq!(
+        Euclidean.distance_bounded(&a, &b, f32::INFINITY),
+        Some(16.0)
+    );
+    assert_eq!(Euclidean.distance_bounded(&a, &b, 16.0), Some(16.0));
+    assert_eq!(Euclidean.distance_bounded(&a, &b, 10.0), None);
+    assert_eq!(
+        SquaredEuclidean.distance_bounded(&a, &b, 256.0),
+        Some(256.0)
+    );
+    assert_eq!(SquaredEuclidean.distance_bounded(&a, &b, 100.0), None);
+    assert_eq!(Manhattan.distance_bounded(&a, &b, 250.0), Some(250.0));
+    assert_eq!(Manhattan.distance_bounded(&a, &b, 100.0), None);
+    assert_eq!(Chebyshev.distance_bounded(&a, &b, 3.0), Some(3.0));
+    assert_eq!(Chebyshev.distance_bounded(&a, &b, 2.0), None);
+
+    // Metrics without early abandoning compute the full distance
+    assert_eq!(InnerProduct.distance_bounded(&a, &b, -1.0), Some(0.0));
+    let (a, b) = (FloatVec::<Manhattan>::from(a), FloatVec::from(b));
+    assert_eq!(a.distance_bounded(&b, 100.0), None);
+}
+
+#[test]
+fn bounded_search() {
+    static BOUNDED: AtomicUsize = AtomicUsize::new(0);
+
+    // Only overrides `distance_bounded()`, so that batches use it through the default hooks
+    #[derive(Clone, Copy, Debug)]
+    struct Bounded(Point);
+
+    impl instant_distance::Point for Bounded {
+        fn distance(&self, other: &Self) -> f32 {
+            self.0.distance(&other.0)
+        }
+
+        fn distance_bounded(&self, other: &Self, bound: f32) -> Option<f32> {
+            if bound < f32::INFINITY {
+                BOUNDED.fetch_add(1, Ordering::Relaxed);
+            }
+            Some(self.distance(other)).filter(|&distance| distance <= bound)
+        }
+    }
+
+    let seed = ThreadRng::default().random();
+    println!("bounded search (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..512)
+        .map(|_| Bounded(Point(rng.random(), rng.random())))
+        .collect::<Vec<_>>();
+
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(points.clone());
+    let built = BOUNDED.load(Ordering::Relaxed);
+    let mut search = Search::default();
+    let found = hnsw.search(&points[17], &mut search).collect::<Vec<_>>();
+    assert!(BOUNDED.load(Ordering::Relaxed) > built);
+    assert_eq!(found[0].pid, pids[17]);
+    for item in found {
+        assert_eq!(item.distance, points[17].distance(item.point));
+    }
+}
+
+#[test]
+fn simd_kernels() {
+    let seed = ThreadRng::default().random();
+    println!("simd kernels (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let levels = [
+        Level::Scalar,
+        Level::Sse2,
+        Level::Avx2,
+        Level::Avx512,
+        Level::Neon,
+    ];
+
+    let close = |x: f32, y: f32| (x - y).abs() <= 1e-4 * y.abs().max(1.0);
+    for len in 0..70 {
+        let a = (0..len)
+            .map(|_| rng.random::<f32>() - 0.5)
+            .collect::<Vec<_>>();
+        let b = (0..len)
+            .map(|_| rng.random::<f32>() - 0.5)
+            .collect::<Vec<_>>();
+        let l2 = a.iter().zip(&b).map(|(a, b)| (a - b) * (a - b)).sum();
+        let dot = a.iter().zip(&b).map(|(a, b)| a * b).sum::<f32>();
+        let norms = a.iter().map(|a| a * a).sum::<f32>() * b.iter().map(|b| b * b).sum::<f32>();
+        let cosine = match norms == 0.0 {
+            true => 1.0,
+            false => 1.0 - dot / norms.sqrt(),
+        };
+
+        assert!(close(simd::l2_squared(&a, &b), l2));
+        assert!(close(simd::dot(&a, &b), dot));
+        assert!(close(simd::cosine(&a, &b), cosine));
+        for level in levels.into_iter().filter(|level| level.is_supported()) {
+            assert!(close(level.l2_squared(&a, &b), l2), "{level:?}");
+            assert!(close(level.dot(&a, &b), dot), "{level:?}");
+            assert!(close(level.cosine(&a, &b), cosine), "{level:?}");
+        }
+    }
+
+    for len in 0..10 {
+        let a = (0..len).map(|_| rng.random::<u64>()).collect::<Vec<_>>();
+        let b = (0..len).map(|_| rng.random::<u64>()).collect::<Vec<_>>();
+        let hamming = a
+            .iter()
+            .zip(&b)
+            .map(|(a, b)| (0..64).filter(|i| (a >> i) & 1 != (b >> i) & 1).count() as u32)
+            .sum::<u32>();
+
+        assert_eq!(simd::hamming(&a, &b), hamming);
+        for level in levels.into_iter().filter(|level| level.is_supported()) {
+            assert_eq!(level.hamming(&a, &b), hamming, "{level:?}");
+        }
+    }
+
+    assert!(Level::detect().is_supported());
+    assert_eq!(simd::cosine(&[0.0; 20], &[1.0; 20]), 1.0);
+}
+
+#[test]
+fn float_vectors() {
+    let seed = ThreadRng::default().random();
+    println!("float vectors (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let vectors = (0..256)
+        .map(|_| {
+            (0..8)
+                .map(|_| rng.random::<f32>() - 0.5)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let points = vectors
+        .iter()
+        .map(|v| FloatVec::<Cosine>::from(v.clone()))
+        .collect::<Vec<_>>();
+    let query = points[17].clone();
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(points);
+    let mut search = Search::default();
+    let first = hnsw.search(&query, &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+    assert!(first.distance < 1e-6);
+
+    let points = vectors
+        .iter()
+        .map(|v| FloatArray::<8, Manhattan>::from(<[f32; 8]>::try_from(&v[..]).unwrap()))
+        .collect::<Vec<_>>();
+    let query = points[17];
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(points);
+    let first = hnsw.search(&query, &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+    assert_eq!(first.distance, 0.0);
+}
+
+#[test]
+#[cfg(feature = "half")]
+fn half_vectors() {
+    let seed = ThreadRng::default().random();
+    println!("half vectors (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let vectors = (0..256)
+        .map(|_| {
+            (0..8)
+                .map(|_| rng.random::<f32>() - 0.5)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let a = F16Vec::<Euclidean>::from_f32(&vectors[0]);
+    let b = F16Vec::<Euclidean>::from_f32(&vectors[1]);
+    let exact = Euclidean.distance(&vectors[0], &vectors[1]);
+    assert!((a.distance(&b) - exact).abs() < 1e-2);
+    assert!((Euclidean.distance(&a.to_f32(), &b.to_f32()) - a.distance(&b)).abs() < 1e-6);
+
+    let points = vectors
+        .iter()
+        .map(|v| Bf16Vec::<Cosine>::from_f32(v))
+        .collect::<Vec<_>>();
+    let query = points[17].clone();
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(points);
+    let mut search = Search::default();
+    let first = hnsw.search(&query, &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+    assert!(first.distance < 1e-6);
+}
+
+#[test]
+fn bit_vectors() {
+    let a = BitArray::<2>::from_signs(&[1.0, -1.0, 0.0, 0.5]);
+    assert_eq!(a.into_inner(), [0b1001, 0]);
+    let b = BitVec::from_signs(&[-1.0; 70]);
+    assert_eq!(&*b, &[0, 0]);
+    assert_eq!(BitVec::from_signs(&[1.0; 65]).distance(&b), 65.0);
+    assert_eq!(Hamming.distance(&a, &BitArray::new([0b0110, 1])), 5.0);
+
+    let seed = ThreadRng::default().random();
+    println!("bit vectors (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..256)
+        .map(|_| BitArray::new([rng.random(), rng.random(), rng.random()]))
+        .collect::<Vec<BitArray<3>>>();
+
+    let query = points[17];
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(points);
+    let mut search = Search::default();
+    let first = hnsw.search(&query, &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+    assert_eq!(first.distance, 0.0);
+}
+
+#[test]
+fn arena() {
+    let seed = ThreadRng::default().random();
+    println!("arena (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let values = (0..256 * 37).map(|_| rng.random()).collect::<Vec<f32>>();
+    let arena = Arena::from_flat(37, &values);
+    assert_eq!(arena.len(), 256);
+    assert_eq!(arena.get(17), Some(&values[17 * 37..18 * 37]));
+    assert_eq!(arena.get(256), None);
+
+    let builders = [
+        Builder::default(),
+        Builder::default().vamana(Some(Vamana::default())),
+        Builder::default().reorder(Some(Reorder::Bfs)),
+    ];
+
+    let mut search = Search::default();
+    for builder in builders {
+        let (hnsw, pids) = builder
+            .seed(seed)
+            .build_hnsw_with_store(arena.clone(), Euclidean);
+        for (i, vector) in arena.iter().enumerate() {
+            assert_eq!(&hnsw[pids[i]], vector);
+        }
+
+        let first = hnsw
+            .search(arena.get(17).unwrap(), &mut search)
+            .next()
+            .unwrap();
+        assert_eq!(first.pid, pids[17]);
+        assert_eq!(first.distance, 0.0);
+    }
+
+    let map = Builder::default().seed(seed).build_with_store(
+        arena.clone(),
+        (0..arena.len()).collect(),
+        Euclidean,
+    );
+    for item in map.search(arena.get(17).unwrap(), &mut search).take(8) {
+        assert_eq!(item.point, arena.get(*item.value).unwrap());
+    }
+}
+
+#[test]
+fn shared_store() {
+    let seed = ThreadRng::default().random();
+    println!("shared store (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let values = (0..256 * 16).map(|_| rng.random()).collect::<Vec<f32>>();
+    let store = Arc::new(Flat::new(16, values.clone()));
+
+    // Indexes with different parameters share the store, leaving its points in place
+    let mut search = Search::default();
+    for builder in [Builder::default().ef_construction(50), Builder::default()] {
+        let (hnsw, pids) = builder
+            .seed(seed)
+            .build_hnsw_with_store(Mapped::new(store.clone()), Euclidean);
+        for (i, &pid) in pids.iter().enumerate() {
+            assert_eq!(hnsw.store().id(pid), PointId::from(i as u32));
+        }
+
+        let first = hnsw
+            .search(&values[17 * 16..18 * 16], &mut search)
+            .next()
+            .unwrap();
+        assert_eq!(hnsw.store().id(first.pid), PointId::from(17));
+        assert_eq!(first.distance, 0.0);
+    }
+    assert_eq!(store.point(PointId::from(17)), &values[17 * 16..18 * 16]);
+
+    // Points are computed once, when first needed
+    let computed = AtomicUsize::new(0);
+    let lazy = Lazy::new(64, |pid: PointId| {
+        computed.fetch_add(1, Ordering::Relaxed);
+        let i = pid.into_inner() as f32;
+        Point(i, -i)
+    });
+    let (hnsw, pids) = Builder::default()
+        .seed(seed)
+        .build_hnsw_with_store(Mapped::new(&lazy), PointMetric);
+    assert_eq!(computed.load(Ordering::Relaxed), 64);
+    assert_eq!(
+        hnsw.search(&Point(3.1, -3.1), &mut search)
+            .next()
+            .unwrap()
+            .pid,
+        pids[3]
+    );
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn store_serialization() {
+    // Arenas of vectors without values load with the same number of vectors
+    let mut arena = Arena::new(0);
+    arena.push(&[]);
+    arena.push(&[]);
+    let bytes = bincode::serialize(&arena).unwrap();
+    assert_eq!(bincode::deserialize::<Arena>(&bytes).unwrap().len(), 2);
+
+    let arena = Arena::from_flat(3, &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    let bytes = bincode::serialize(&arena).unwrap();
+    let loaded = bincode::deserialize::<Arena>(&bytes).unwrap();
+    assert_eq!(
+        loaded.iter().collect::<Vec<_>>(),
+        arena.iter().collect::<Vec<_>>()
+    );
+
+    let flat = Flat::new(2, vec![1.0, 2.0, 3.0, 4.0]);
+    let bytes = bincode::serialize(&flat).unwrap();
+    assert_eq!(
+        bincode::deserialize::<Flat<Vec<f32>>>(&bytes)
+            .unwrap()
+            .len(),
+        2
+    );
+    let bytes = bincode::serialize(&(0_usize, vec![1.0_f32])).unwrap();
+    assert!(bincode::deserialize::<Flat<Vec<f32>>>(&bytes).is_err());
+
+    // Identifiers beyond the end of the underlying store are rejected
+    let mapped = Mapped::new(flat);
+    let bytes = bincode::serialize(&mapped).unwrap();
+    let loaded = bincode::deserialize::<Mapped<Flat<Vec<f32>>>>(&bytes).unwrap();
+    assert_eq!(loaded.id(PointId::from(1)), PointId::from(1));
+    let corrupt = (
+        Flat::new(2, vec![1.0, 2.0]),
+        vec![PointId::from(0), PointId::from(1)],
+    );
+    let bytes = bincode::serialize(&corrupt).unwrap();
+    assert!(bincode::deserialize::<Mapped<Flat<Vec<f32>>>>(&bytes).is_err());
+}
+
+#[test]
+fn sparse_vectors() {
+    let seed = ThreadRng::default().random();
+    println!("sparse vectors (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut dense = |nonzero: f64| {
+        (0..512)
+            .map(|_| match rng.random_bool(nonzero) {
+                true => rng.random::<f32>() - 0.5,
+                false => 0.0,
+            })
+            .collect::<Vec<_>>()
+    };
+
+    // Both the merging and the galloping kernels match the dense inner product
+    let (a, b, c) = (dense(0.2), dense(0.2), dense(0.01));
+    for (a, b) in [(&a, &b), (&a, &c), (&c, &b)] {
+        let (sa, sb) = (
+            SparseVec::<InnerProduct>::from_dense(a),
+            SparseVec::from_dense(b),
+        );
+        let dot = a.iter().zip(b).map(|(a, b)| a * b).sum::<f32>();
+        assert!((sa.dot(&sb) - dot).abs() < 1e-4);
+        assert!((sa.distance(&sb) + dot).abs() < 1e-4);
+
+        let (sa, sb) = (SparseVec::<Cosine>::from_dense(a), SparseVec::from_dense(b));
+        assert!((sa.distance(&sb) - Cosine.distance(a, b)).abs() < 1e-4);
+    }
+
+    let pairs = SparseVec::<Cosine>::from_pairs([(7, 1.0), (3, 2.0), (7, 0.5)]);
+    assert_eq!(pairs.indices(), [3, 7]);
+    assert_eq!(pairs.values(), [2.0, 1.5]);
+    assert_eq!(pairs.norm(), 2.5);
+
+    let points = (0..256)
+        .map(|_| SparseVec::<Cosine>::from_dense(&dense(0.05)))
+        .collect::<Vec<_>>();
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(points.clone());
+    let mut search = Search::default();
+    let first = hnsw.search(&points[17], &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+    assert!(first.distance < 1e-6);
+
+    // The metric can also be passed to the builder, overriding the vectors' own metric
+    let points = points
+        .iter()
+        .map(|p| SparseVec::<InnerProduct>::new(p.indices(), p.values()))
+        .collect::<Vec<_>>();
+    let map =
+        Builder::default()
+            .seed(seed)
+            .build_with_metric(points.clone(), (0..256).collect(), Cosine);
+    let first = map.search(&points[17], &mut search).next().unwrap();
+    assert_eq!(*first.value, 17);
+    assert!(first.distance < 1e-6);
+}
+
+#[test]
+fn sets() {
+    let a = SortedSet::new([5, 1, 3, 3, 9]);
+    assert_eq!(a.elements(), [1, 3, 5, 9]);
+    assert!(a.contains(&5) && !a.contains(&4));
+    let b = SortedSet::new([3, 4, 5]);
+    assert_eq!(a.intersection_len(&b), 2);
+    assert_eq!(a.distance(&b), 1.0 - 2.0 / 5.0);
+    assert_eq!(a.distance(&a), 0.0);
+    let empty = SortedSet::<u64>::new([]);
+    assert_eq!(empty.distance(&empty), 0.0);
+    assert_eq!(empty.distance(&a), 1.0);
+
+    let seed = ThreadRng::default().random();
+    println!("sets (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let sets = (0..256)
+        .map(|_| SortedSet::new((0..64).map(|_| rng.random_range(0..256u64))))
+        .collect::<Vec<_>>();
+
+    // Signatures estimate the Jaccard distance and identify their sets
+    let hasher = MinHasher::new(256, seed);
+    let signatures = sets.iter().map(|set| hasher.sign(set)).collect::<Vec<_>>();
+    assert_eq!(hasher.sign_iter([9, 1, 5, 3, 3]), hasher.sign(&a));
+    for i in 0..16 {
+        let (exact, estimate) = (
+            sets[i].distance(&sets[i + 1]),
+            signatures[i].distance(&signatures[i + 1]),
+        );
+        assert!((exact - estimate).abs() < 0.2);
+        let bounded = signatures[i].distance_bounded(&signatures[i + 1], estimate - 0.05);
+        assert_eq!(bounded, None);
+    }
+
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(sets.clone());
+    let (signed, signed_pids) = Builder::default().seed(seed).build_hnsw(signatures);
+    let mut search = Search::default();
+    let first = hnsw.search(&sets[17], &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+    let first = signed
+        .search(&hasher.sign(&sets[17]), &mut search)
+        .next()
+        .unwrap();
+    assert_eq!(first.pid, signed_pids[17]);
+}
+
+#[test]
+fn edit_distances() {
+    let lev = |a: &str, b: &str| Text::<Levenshtein>::new(a).distance(&Text::new(b));
+    let dl = |a: &str, b: &str| Text::<DamerauLevenshtein>::new(a).distance(&Text::new(b));
+    assert_eq!(lev("kitten", "sitting"), 3.0);
+    assert_eq!(lev("", "abc"), 3.0);
+    assert_eq!(lev("straße", "strasse"), 2.0);
+    assert_eq!(dl("ca", "abc"), 2.0);
+    assert_eq!(lev("ca", "abc"), 3.0);
+    assert_eq!(dl("abcdef", "badcfe"), 3.0);
+    assert_eq!(Text::<Levenshtein>::new("straße").to_string(), "straße");
+
+    let seed = ThreadRng::default().random();
+    println!("edit distances (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut random = || {
+        let len = rng.random_range(0..12);
+        (0..len)
+            .map(|_| rng.random_range(b'a'..b'e') as char)
+            .collect::<String>()
+    };
+    let texts = (0..96).map(|_| random()).collect::<Vec<_>>();
+
+    // Bounded distances agree with the full distance, and both metrics satisfy the triangle
+    // inequality; transpositions make the Damerau–Levenshtein distance no larger
+    for abc in texts.windows(3) {
+        let (a, b, c) = (&abc[0], &abc[1], &abc[2]);
+        assert!(lev(a, c) <= lev(a, b) + lev(b, c));
+        assert!(dl(a, c) <= dl(a, b) + dl(b, c));
+        assert!(dl(a, b) <= lev(a, b));
+
+        let (ta, tb) = (Text::<Levenshtein>::new(a), Text::new(b));
+        let (da, db) = (Text::<DamerauLevenshtein>::new(a), Text::new(b));
+        for bound in [0.0, 1.0, 2.5, 4.0, 7.0] {
+            let expected = Some(lev(a, b)).filter(|&d| d <= bound);
+            assert_eq!(ta.distance_bounded(&tb, bound), expected);
+            let expected = Some(dl(a, b)).filter(|&d| d <= bound);
+            assert_eq!(da.distance_bounded(&db, bound), expected);
+        }
+    }
+
+    let points = texts.iter().map(|t| Text::new(t)).collect::<Vec<_>>();
+    let map = Builder::default()
+        .seed(seed)
+        .build::<Text<DamerauLevenshtein>, _>(points, texts.clone());
+    let mut search = Search::default();
+    let first = map
+        .search(&Text::new(&texts[17]), &mut search)
+        .next()
+        .unwrap();
+    assert_eq!(first.distance, 0.0);
+    assert_eq!(first.point.to_string(), texts[17]);
+
+    // The metric can also be passed to the builder, overriding the texts' own metric
+    let points = texts.iter().map(|t| Text::new(t)).collect::<Vec<_>>();
+    let map = Builder::default()
+        .seed(seed)
+        .build_with_metric::<Text, _, _>(points, texts.clone(), DamerauLevenshtein);
+    let query = Text::new(&texts[17]);
+    let first = map.search(&query, &mut search).next().unwrap();
+    assert_eq!(first.distance, 0.0);
+    assert_eq!(Levenshtein.distance(&query, first.point), 0.0);
+}
+
+#[test]
+fn reranked() {
+    let seed = ThreadRng::default().random();
+    println!("reranked (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let full = (0..256)
+        .map(|_| {
+            (0..16)
+                .map(|_| rng.random::<f32>() - 0.5)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // Navigate using only the first 4 dimensions of each vector
+    let truncated = full
+        .iter()
+        .map(|v| FloatVec::<Euclidean>::from(v[..4].to_vec()))
+        .collect::<Vec<_>>();
+    let query = truncated[17].clone();
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(truncated.clone());
+
+    let mut ordered = vec![Vec::new(); full.len()];
+    for (vector, pid) in full.iter().zip(&pids) {
+        ordered[pid.into_inner() as usize] = vector.clone();
+    }
+
+    let mut search = Search::default();
+    let results = hnsw
+        .search_reranked(&query, &full[17], &ordered, &Euclidean, &mut search)
+        .map(|item| (item.pid, item.distance))
+        .collect::<Vec<_>>();
+    assert_eq!(results[0], (pids[17], 0.0));
+    assert!(results.windows(2).all(|w| w[0].1 <= w[1].1));
+    for (pid, distance) in &results {
+        let exact = Euclidean.distance(&full[17], &ordered[pid.into_inner() as usize]);
+        assert_eq!(*distance, exact);
+    }
+
+    // Load the full vectors through a closure, using the map's values
+    let map = Builder::default()
+        .seed(seed)
+        .build(truncated, (0..full.len()).collect());
+    let source = |pid: PointId| &full[map.values[pid.into_inner() as usize]];
+    let first = map
+        .search_reranked(&query, &full[17], &source, &Euclidean, &mut search)
+        .next()
+        .unwrap();
+    assert_eq!(*first.value, 17);
+    assert_eq!(first.distance, 0.0);
+}
+
+#[test]
+fn batched_distances() {
+    static BATCHES: AtomicUsize = AtomicUsize::new(0);
+    static ABANDONED: AtomicUsize = AtomicUsize::new(0);
+
+    #[derive(Clone, Copy, Debug)]
+    struct Batched(Point);
+
+    impl instant_distance::Point for Batched {
+        fn distance(&self, other: &Self) -> f32 {
+            self.0.distance(&other.0)
+        }
+
+        fn distances(&self, others: &[&Self], bound: f32, out: &mut [Option<f32>]) {
+            assert_eq!(others.len(), out.len());
+            BATCHES.fetch_add(1, Ordering::Relaxed);
+            for (other, out) in others.iter().zip(out) {
+                let distance = self.0.distance(&other.0);
+                *out = match distance > bound {
+                    true => {
+                        ABANDONED.fetch_add(1, Ordering::Relaxed);
+                        None
+                    }
+                    false => Some(distance),
+                };
+            }
+        }
+    }
+
+    let seed = ThreadRng::default().random();
+    println!("batched distances (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..512)
+        .map(|_| Batched(Point(rng.random(), rng.random())))
+        .collect::<Vec<_>>();
+
+    let (hnsw, pids) = Builder::default().seed(seed).build_hnsw(points.clone());
+    let built = BATCHES.load(Ordering::Relaxed);
+    assert!(built > 0);
+
+    // Once `ef` neighbors were found, batches are bounded by the furthest of them
+    let abandoned = ABANDONED.load(Ordering::Relaxed);
+    let mut search = Search::default();
+    let found = hnsw.search(&points[17], &mut search).collect::<Vec<_>>();
+    assert!(BATCHES.load(Ordering::Relaxed) > built);
+    assert!(ABANDONED.load(Ordering::Relaxed) > abandoned);
+    assert_eq!(found[0].pid, pids[17]);
+    assert!(found.windows(2).all(|w| w[0].distance <= w[1].distance));
+    for item in found {
+        assert_eq!(item.distance, points[17].distance(item.point));
+    }
+}
+
+#[test]
+#[cfg(feature = "serde")]
+#[allow(clippy::float_cmp)]
+fn baseline_serialization() {
+    #[derive(Clone, Copy, Debug, serde::Deserialize, serde::Serialize)]
+    struct Grid([f32; 2]);
+
+    impl instant_distance::Point for Grid {
+        fn distance(&self, other: &Self) -> f32 {
+            Euclidean.distance(&self.0, &other.0)
+        }
+    }
+
+    // A map of a 4x4 grid serialized with bincode, before indexes stored their metric
+    let bytes = include_bytes!("baseline.bincode");
+    let map = bincode::deserialize::<instant_distance::HnswMap<Grid, u32>>(bytes).unwrap();
+    let mut search = Search::default();
+    let first = map.search(&Grid([2.0, 1.0]), &mut search).next().unwrap();
+    assert_eq!((*first.value, first.distance), (6, 0.0));
+    assert_eq!(bincode::serialize(&map).unwrap(), bytes);
+}
+
+#[test]
+fn separate_metrics() {
+    let seed = ThreadRng::default().random();
+    println!("separate metrics (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..256)
+        .map(|_| {
+            (0..8)
+                .map(|_| rng.random::<f32>() - 0.5)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // A scaled copy of a point has cosine distance 0, but is far away in Euclidean distance
+    let query = points[17].iter().map(|x| x * 4.0).collect::<Vec<_>>();
+    let mut search = Search::default();
+
+    let (hnsw, pids) = Builder::default()
+        .seed(seed)
+        .build_hnsw_with_metric(points.clone(), Cosine);
+    assert_eq!(*hnsw.metric(), Cosine);
+    let first = hnsw.search(&query, &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+    assert!(first.distance < 1e-6);
+
+    let (distance, nearest) = points
+        .iter()
+        .enumerate()
+        .map(|(i, p)| (OrderedFloat::from(Euclidean.distance(&query, p)), i))
+        .min()
+        .unwrap();
+    let values = (0..points.len()).collect::<Vec<_>>();
+    let map = Builder::default()
+        .seed(seed)
+        .build_with_metric(points, values, Euclidean);
+    let first = map.search(&query, &mut search).next().unwrap();
+    assert_eq!(*first.value, nearest);
+    assert_eq!(first.distance, distance.into_inner());
+}
+
+#[test]
+fn scalar_quantization() {
+    let seed = ThreadRng::default().random();
+    println!("scalar quantization (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let vectors = (0..256)
+        .map(|_| {
+            (0..16)
+                .map(|i| (rng.random::<f32>() - 0.5) * (i + 1) as f32)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for granularity in [Granularity::PerDimension, Granularity::PerVector] {
+        let quantizer = ScalarQuantizer::<Euclidean>::train(&vectors, granularity);
+        for vector in &vectors {
+            let decoded = quantizer.decode(&quantizer.encode(vector));
+            let bound = 16.0 / 255.0;
+            assert!(vector
+                .iter()
+                .zip(&decoded)
+                .all(|(a, b)| (a - b).abs() <= bound));
+        }
+
+        let codes = vectors.iter().map(|v| quantizer.encode(v)).collect();
+        let map = Builder::default().seed(seed).build_with_metric(
+            codes,
+            (0..vectors.len()).collect(),
+            quantizer,
+        );
+
+        let query = &vectors[17];
+        let mut search = Search::default();
+        let found = map.search(&map.metric().encode(query), &mut search);
+        let pids = found.map(|item| item.pid).collect::<Vec<_>>();
+        let nearest = map.metric().rescore(query, pids, |pid| {
+            &vectors[map.values[pid.into_inner() as usize]]
+        });
+        assert_eq!(map.values[nearest[0].pid.into_inner() as usize], 17);
+        assert_eq!(nearest[0].distance(), 0.0);
+        assert!(nearest
+            .windows(2)
+            .all(|w| w[0].distance() <= w[1].distance()));
+
+        // The full-precision query is compared to the decoded points
+        let found = map.search(query.as_slice(), &mut search);
+        assert!(found.take(5).any(|item| *item.value == 17));
+    }
+}
+
+#[test]
+fn kmeans() {
+    let seed = ThreadRng::default().random();
+    println!("kmeans (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Blobs of vectors on a grid, with more blobs than are assigned without an index
+    let centers = (0..100)
+        .map(|i| {
+            let mut center = vec![(i % 10) as f32 * 50.0, (i / 10) as f32 * 50.0];
+            center.extend((0..6).map(|_| rng.random::<f32>() * 50.0));
+            center
+        })
+        .collect::<Vec<_>>();
+    let vectors = (0..2000)
+        .map(|i| {
+            let center = &centers[i % centers.len()];
+            center
+                .iter()
+                .map(|x| x + (rng.random::<f32>() - 0.5) * 0.1)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    for kmeans in [
+        KMeans::new(100).seed(seed),
+        KMeans::new(100)
+            .seed(seed)
+            .mini_batch(Some(1000))
+            .iterations(8),
+    ] {
+        let clusters = kmeans.fit(&vectors);
+        assert_eq!(clusters.len(), 100);
+        assert_eq!(clusters.assignments().len(), vectors.len());
+
+        // k-means may settle in a local optimum, but nearly all blobs get a cluster of their own
+        let separate = (0..centers.len())
+            .filter(|&blob| {
+                let cluster = clusters.assignments()[blob];
+                clusters
+                    .assignments()
+                    .iter()
+                    .enumerate()
+                    .all(|(i, &c)| (c == cluster) == (i % centers.len() == blob))
+            })
+            .count();
+        assert!(separate >= 95);
+
+        // Assignments through the temporary index mostly agree with an exhaustive comparison
+        let agree = vectors
+            .iter()
+            .zip(clusters.assignments())
+            .filter(|(v, &cluster)| clusters.assign(v) == cluster)
+            .count();
+        assert!(agree as f32 >= vectors.len() as f32 * 0.99);
+    }
+
+    // With few clusters, each blob ends up in a cluster of its own
+    let few = (0..vectors.len())
+        .filter(|i| i % centers.len() < 4)
+        .collect::<Vec<_>>();
+    let subset = few.iter().map(|&i| &vectors[i][..]).collect::<Vec<_>>();
+    let clusters = KMeans::new(4).seed(seed).fit(&subset);
+    for (a, &i) in few.iter().enumerate() {
+        for (b, &j) in few.iter().enumerate() {
+            let same = clusters.assignments()[a] == clusters.assignments()[b];
+            assert_eq!(same, i % centers.len() == j % centers.len());
+        }
+    }
+    // Each value is off by at most 0.05 from its blob's center, with a variance of 1/1200
+    assert!(clusters.inertia(&subset) < few.len() as f32 * 8.0 / 1200.0 * 1.5);
+    assert_eq!(KMeans::new(10).fit(&vectors[..3]).len(), 3);
+}
+
+#[test]
+fn product_quantization() {
+    let seed = ThreadRng::default().random();
+    println!("product quantization (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let vectors = (0..320)
+        .map(|_| (0..8).map(|_| rng.random::<f32>()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+
+    let quantizer = ProductQuantizer::<Euclidean>::train(&vectors, 4, seed);
+    let codes = vectors
+        .iter()
+        .map(|v| quantizer.encode(v))
+        .collect::<Vec<_>>();
+    assert!(codes.iter().all(|code| code.codes().len() == 4));
+
+    // The table gives the exact distance to the decoded vectors
+    let table = quantizer.table(&vectors[0]);
+    for code in &codes[..16] {
+        let decoded = quantizer.decode(code);
+        let exact = Euclidean.distance(&vectors[0], &decoded);
+        assert!((table.distance(code) - exact).abs() < 1e-4);
+    }
+
+    let (hnsw, pids) = Builder::default()
+        .seed(seed)
+        .build_hnsw_with_metric(codes.clone(), quantizer);
+    let query = hnsw.metric().decode(&codes[17]);
+    let mut search = Search::default();
+    let first = hnsw.search_asymmetric(&query, &mut search).next().unwrap();
+    assert_eq!(first.point, &codes[17]);
+    assert!(first.distance < 1e-6);
+
+    let found = hnsw.search_asymmetric(&vectors[17], &mut search);
+    assert!(found.take(5).any(|item| item.pid == pids[17]));
+
+    let table = hnsw.metric().table(&vectors[17]);
+    let found = hnsw.search(&table, &mut search);
+    assert!(found.take(5).any(|item| item.pid == pids[17]));
+}
+
+#[test]
+fn geo() {
+    // London to Paris, and the survey line from Flinders Peak to Buninyong
+    let (london, paris) = (LatLng::new(51.5074, -0.1278), LatLng::new(48.8566, 2.3522));
+    assert!((Haversine.distance(&london, &paris) - 343_556.0).abs() < 10.0);
+    let flinders = LatLng::new(-37.951_033_42, 144.424_867_89);
+    let buninyong = LatLng::new(-37.652_821_14, 143.926_495_54);
+    assert!((Vincenty.distance(&flinders, &buninyong) - 54_972.27).abs() < 0.01);
+    assert_eq!(Vincenty.distance(&paris, &paris), 0.0);
+
+    // Nearly antipodal positions fall back to the haversine distance
+    let (a, b) = (LatLng::new(0.0, 0.0), LatLng::new(0.5, 179.7));
+    assert_eq!(Vincenty.distance(&a, &b), Haversine.distance(&a, &b));
+
+    let seed = ThreadRng::default().random();
+    println!("geo (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let points = (0..2048)
+        .map(|_| LatLng::new(rng.random_range(50.0..54.0), rng.random_range(3.0..8.0)))
+        .collect::<Vec<_>>();
+    let (hnsw, pids) = Builder::default()
+        .seed(seed)
+        .ef_search(16)
+        .build_hnsw(points.clone());
+
+    // The radius search is not limited to `ef_search` results
+    let center = LatLng::new(52.0, 5.5);
+    let mut search = Search::default();
+    let found = hnsw
+        .search_radius(&center, 50_000.0, &mut search)
+        .map(|item| item.pid)
+        .collect::<HashSet<_>>();
+    let expected = (0..points.len())
+        .filter(|&i| center.distance(&points[i]) <= 50_000.0)
+        .map(|i| pids[i])
+        .collect::<HashSet<_>>();
+    assert!(expected.len() > 16);
+    assert!(found.is_subset(&expected));
+    assert!(found.len() as f32 >= expected.len() as f32 * 0.95);
+}
+
+#[test]
+fn transforms() {
+    let seed = ThreadRng::default().random();
+    println!("transforms (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    // Vectors in a three-dimensional subspace, offset from the origin
+    let basis = (0..3 * 16)
+        .map(|_| rng.random::<f32>() - 0.5)
+        .collect::<Vec<_>>();
+    let vectors = (0..256)
+        .map(|_| {
+            let weights = [(); 3].map(|_| rng.random::<f32>() * 4.0 - 2.0);
+            (0..16)
+                .map(|i| 5.0 + (0..3).map(|j| weights[j] * basis[j * 16 + i]).sum::<f32>())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let normalized = Pipeline::new(16).normalize().apply(&vectors[0]);
+    assert!((simd::dot(&normalized, &normalized) - 1.0).abs() < 1e-5);
+    assert_eq!(Pipeline::new(2).normalize().apply(&[0.0, 0.0]), [0.0, 0.0]);
+
+    let centered = Pipeline::new(16).center(&vectors);
+    let sum = vectors
+        .iter()
+        .map(|v| centered.apply(v))
+        .fold(vec![0.0; 16], |sum, v| {
+            sum.iter().zip(&v).map(|(s, x)| s + x).collect::<Vec<_>>()
+        });
+    assert!(sum.iter().all(|s| s.abs() < 1e-2));
+
+    // Three principal components preserve the distances within the subspace
+    let pca = Pipeline::new(16).pca(&vectors, 3);
+    assert_eq!((pca.input_dimensions(), pca.dimensions()), (16, 3));
+    let projected = vectors.iter().map(|v| pca.apply(v)).collect::<Vec<_>>();
+    for i in 0..16 {
+        let original = Euclidean.distance(&vectors[i], &vectors[i + 1]);
+        let reduced = Euclidean.distance(&projected[i], &projected[i + 1]);
+        assert!((original - reduced).abs() < 1e-3 * original.max(1.0));
+    }
+
+    let projection = Pipeline::new(16).random_projection(8, seed);
+    assert_eq!(projection.apply(&vectors[0]).len(), 8);
+    assert_eq!(projection, Pipeline::new(16).random_projection(8, seed));
+
+    // Queries go through the same transforms as the points
+    let transformed = Transformed::new(Pipeline::new(16).normalize().pca(&vectors, 3), Euclidean);
+    let points = vectors
+        .iter()
+        .map(|v| transformed.transform(v))
+        .collect::<Vec<_>>();
+    let (hnsw, pids) = Builder::default()
+        .seed(seed)
+        .build_hnsw_with_metric(points, transformed);
+    let scaled = vectors[17].iter().map(|x| x * 3.0).collect::<Vec<_>>();
+    let query = hnsw.metric().transform(&scaled);
+    let mut search = Search::default();
+    let first = hnsw.search(&query, &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+
+    // Untransformed queries are transformed by the index's pipeline
+    let first = hnsw.search_raw(&scaled, &mut search).next().unwrap();
+    assert_eq!(first.pid, pids[17]);
+}
+
+#[test]
+fn mips() {
+    let seed = ThreadRng::default().random();
+    println!("mips (seed = {seed})");
+    let mut rng = StdRng::seed_from_u64(seed);
+    let vectors = (0..1024)
+        .map(|_| {
+            let scale = rng.random_range(0.1..4.0);
+            (0..16)
+                .map(|_| (rng.random::<f32>() - 0.5) * scale)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let mips = Mips::fit(&vectors);
+    let points = vectors.iter().map(|v| mips.augment(v)).collect::<Vec<_>>();
+    assert_eq!(points[17].vector(), vectors[17]);
+    let (hnsw, pids) = Builder::default()
+        .seed(seed)
+        .build_hnsw_with_metric(points, mips);
+
+    let (mut search, mut found) = (Search::default(), 0);
+    for _ in 0..32 {
+        let query = (0..16)
+            .map(|_| rng.random::<f32>() - 0.5)
+            .collect::<Vec<_>>();
+        let mut dots = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (OrderedFloat(-simd::dot(&query, v)), i))
+            .collect::<Vec<_>>();
+        dots.sort_unstable();
+
+        let items = hnsw.search(&query[..], &mut search).take(10);
+        let result = items.map(|item| item.pid).collect::<HashSet<_>>();
+        found += dots[..10]
+            .iter()
+            .filter(|(_, i)| result.contains(&pids[*i]))
+            .count();
+
+        let first = hnsw.search(&query[..], &mut search).next().unwrap();
+        assert_eq!(first.distance, -simd::dot(&query, first.point.vector()));
+    }
+
+    let recall = found as f32 / 320.0;
+    println!("mips recall: {recall}");
+    assert!(recall > 0.9);
+}
+
 fn randomized(builder: Builder) -> (u64, usize) {
     let seed = ThreadRng::default().random();
     let mut rng = StdRng::seed_from_u64(seed);